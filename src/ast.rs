@@ -1,10 +1,69 @@
 use alloc::{rc::Rc, vec::Vec};
+use core::fmt;
 use dashu_int::IBig;
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+use crate::frontend::Span;
+
+/// A unary or binary operator, displayed as its source text. \
+/// Example
+/// ```rust
+/// use cara::ast::Op;
+///
+/// let ops = [
+///     (Op::Add, "+"),
+///     (Op::Sub, "-"),
+///     (Op::Mul, "*"),
+///     (Op::Div, "/"),
+///     (Op::Eq, "=="),
+///     (Op::Ne, "!="),
+///     (Op::Ge, ">="),
+///     (Op::LShift, "<<"),
+///     (Op::Gt, ">"),
+///     (Op::Le, "<="),
+///     (Op::RShift, ">>"),
+///     (Op::Lt, "<"),
+///     (Op::Or, "||"),
+///     (Op::And, "&&"),
+///     (Op::Rem, "%"),
+///     (Op::Pow, "**"),
+///     (Op::Not, "!"),
+///     (Op::BitAnd, "&"),
+///     (Op::BitOr, "|"),
+///     (Op::BitXor, "^"),
+/// ];
+/// for (op, text) in ops {
+///     assert_eq!(op.to_string(), text);
+/// }
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Op {
+    /// Addition, which also concatenates strings and lists. The length of a
+    /// concatenated list is bounded like template lists. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::{CrValue, Error};
+    /// use cara::CaraError;
+    ///
+    /// assert_eq!(cara::eval(r#""ab" + "cd""#).unwrap(), CrValue::Str("abcd".into()));
+    /// let error = cara::eval(r#""ab" + 1"#).unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::NonIntCalc)));
+    ///
+    /// assert_eq!(cara::eval("[1, 2] + [3]").unwrap(), cara::eval("[1, 2, 3]").unwrap());
+    /// assert_eq!(cara::eval("[] + [1]").unwrap(), cara::eval("[1]").unwrap());
+    /// let error = cara::eval("var x = [1]; for i in (0, 40) { x = x + x; }").unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::ListTooLong)));
+    /// ```
     Add,
     Sub,
+    /// Multiplication, which also repeats a string a whole number of times. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::CrValue;
+    ///
+    /// assert_eq!(cara::eval(r#""ab" * 3"#).unwrap(), CrValue::Str("ababab".into()));
+    /// assert_eq!(cara::eval(r#""ab" * 0"#).unwrap(), CrValue::Str("".into()));
+    /// ```
     Mul,
     Div,
     Eq,
@@ -18,34 +77,797 @@ pub enum Op {
     Or,
     And,
     Rem,
+    /// Exponentiation, which binds tighter than the other binary operators and
+    /// groups from the right. \
+    /// Example
+    /// ```rust
+    /// use cara::ast::{AstNodes, Op};
+    /// use cara::backend::{CrValue, Error};
+    /// use cara::frontend::{Lexer, Parser, Token};
+    /// use cara::CaraError;
+    ///
+    /// let mut lexer = Lexer::new("2 ** 3 * 2".into());
+    /// assert_eq!(lexer.next_token(), Ok(Some(Token::Number(2.into()))));
+    /// assert_eq!(lexer.next_token(), Ok(Some(Token::Operator(Op::Pow))));
+    ///
+    /// let lexer = Lexer::new("2 * 3 ** 2".into());
+    /// let (ast, _) = Parser::new(lexer).parse_compile_unit().unwrap();
+    /// let AstNodes::CompileUnit(nodes) = ast else { unreachable!() };
+    /// assert!(matches!(&nodes[0], AstNodes::BinaryOp(_, Op::Mul, ..)));
+    ///
+    /// assert_eq!(cara::eval("2 ** 3 ** 2").unwrap(), CrValue::Number(512.into()));
+    /// assert_eq!(cara::eval("2 ** 3 * 2").unwrap(), CrValue::Number(16.into()));
+    /// let error = cara::eval("2 ** -1").unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::NonIntCalc)));
+    ///
+    /// // Unary minus binds tighter than every binary operator except `**`.
+    /// assert_eq!(cara::eval("-2 ** 2").unwrap(), CrValue::Number((-4).into()));
+    /// assert_eq!(cara::eval("-1 + 2").unwrap(), CrValue::Number(1.into()));
+    /// assert_eq!(cara::eval("-2 * 3 + 1").unwrap(), CrValue::Number((-5).into()));
+    /// ```
+    Pow,
+    /// Logical negation, which gives a bool from the operand's truthiness. \
+    /// Example
+    /// ```rust
+    /// use cara::ast::Op;
+    /// use cara::backend::CrValue;
+    /// use cara::frontend::{Lexer, Token};
+    ///
+    /// let mut lexer = Lexer::new("!!x != 0".into());
+    /// assert_eq!(lexer.next_token(), Ok(Some(Token::Operator(Op::Not))));
+    /// assert_eq!(lexer.next_token(), Ok(Some(Token::Operator(Op::Not))));
+    /// lexer.next_token().unwrap();
+    /// assert_eq!(lexer.next_token(), Ok(Some(Token::Operator(Op::Ne))));
+    ///
+    /// assert_eq!(cara::eval("!0").unwrap(), CrValue::Bool(true));
+    /// assert_eq!(cara::eval("!5").unwrap(), CrValue::Bool(false));
+    /// assert_eq!(cara::eval("!!3").unwrap(), CrValue::Bool(true));
+    /// assert_eq!(cara::eval("!true || true").unwrap(), CrValue::Bool(true));
+    /// ```
+    Not,
+    /// Bitwise operators on numbers, which bind looser than arithmetic but tighter
+    /// than comparisons. \
+    /// Example
+    /// ```rust
+    /// use cara::ast::Op;
+    /// use cara::backend::CrValue;
+    /// use cara::frontend::{Lexer, Token};
+    ///
+    /// let mut lexer = Lexer::new("& && | || ^".into());
+    /// for op in [Op::BitAnd, Op::And, Op::BitOr, Op::Or, Op::BitXor] {
+    ///     assert_eq!(lexer.next_token(), Ok(Some(Token::Operator(op))));
+    /// }
+    ///
+    /// let value = cara::eval("[6 & 3, 6 | 1, 5 ^ 1, 1 | 2 == 3, (-1) & 255]").unwrap();
+    /// assert_eq!(value, cara::eval("[2, 7, 4, true, 255]").unwrap());
+    /// ```
+    BitAnd,
+    BitOr,
+    BitXor,
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Op {
+    /// Every operator, in the order they are declared.
+    pub const ALL: [Self; 20] = [
+        Self::Add,
+        Self::Sub,
+        Self::Mul,
+        Self::Div,
+        Self::Eq,
+        Self::Ne,
+        Self::Ge,
+        Self::LShift,
+        Self::Gt,
+        Self::Le,
+        Self::RShift,
+        Self::Lt,
+        Self::Or,
+        Self::And,
+        Self::Rem,
+        Self::Pow,
+        Self::Not,
+        Self::BitAnd,
+        Self::BitOr,
+        Self::BitXor,
+    ];
+
+    /// Returns the operator written as `text`, the reverse of displaying it. \
+    /// Example
+    /// ```rust
+    /// use cara::ast::Op;
+    ///
+    /// for op in Op::ALL {
+    ///     assert_eq!(Op::from_str(&op.to_string()), Some(op));
+    /// }
+    /// assert_eq!(Op::from_str("<<"), Some(Op::LShift));
+    /// assert_eq!(Op::from_str("=>"), None);
+    /// assert_eq!(Op::from_str("="), None);
+    /// ```
+    #[allow(clippy::should_implement_trait)]
+    #[must_use]
+    pub fn from_str(text: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|op| op.as_str() == text)
+    }
+
+    /// Returns the operator's source text.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Add => "+",
+            Self::Sub => "-",
+            Self::Mul => "*",
+            Self::Div => "/",
+            Self::Eq => "==",
+            Self::Ne => "!=",
+            Self::Ge => ">=",
+            Self::LShift => "<<",
+            Self::Gt => ">",
+            Self::Le => "<=",
+            Self::RShift => ">>",
+            Self::Lt => "<",
+            Self::Or => "||",
+            Self::And => "&&",
+            Self::Rem => "%",
+            Self::Pow => "**",
+            Self::Not => "!",
+            Self::BitAnd => "&",
+            Self::BitOr => "|",
+            Self::BitXor => "^",
+        }
+    }
 }
 
 /// This is the AST nodes definition.
+/// Identifiers and string literals are interned ids unless the AST comes from
+/// [`Parser::parse_named`](crate::frontend::Parser::parse_named).
 #[derive(Debug, Clone)]
-pub enum AstNodes {
-    Assign(usize, Option<Rc<AstNodes>>, Rc<AstNodes>),
-    CompileUnit(Vec<AstNodes>),
-    BinaryOp(Rc<AstNodes>, Op, Rc<AstNodes>),
-    UnaryOp(Op, Rc<AstNodes>),
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AstNodes<I = usize> {
+    /// A variable, the indices leading to the element assigned if it's a list, and
+    /// the value assigned. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::CrValue;
+    ///
+    /// let value = cara::eval("var m = [[1, 2], [3, 4]]; m[0][1] = 9; m").unwrap();
+    /// let expected = cara::eval("[[1, 9], [3, 4]]").unwrap();
+    /// assert_eq!(value, expected);
+    /// ```
+    Assign(I, Vec<AstNodes<I>>, Rc<AstNodes<I>>),
+    /// Variables assigned the elements of a list at once, like `a, b = b, a`. Several
+    /// values on the right are parsed as a list, so they are all evaluated before
+    /// any variable changes. \
+    /// Example
+    /// ```rust
+    /// let value = cara::eval("var a = 1; var b = 2; a, b = b, a; [a, b]").unwrap();
+    /// assert_eq!(value, cara::eval("[2, 1]").unwrap());
+    ///
+    /// let value = cara::eval("var a = 0; var b = 0; var pair = [3, 4]; a, b = pair; a * b");
+    /// assert_eq!(value.unwrap(), cara::eval("12").unwrap());
+    /// ```
+    Unpack(Vec<I>, Rc<AstNodes<I>>),
+    CompileUnit(Vec<AstNodes<I>>),
+    /// Two operands, the operator between them and where the operator is, which
+    /// errors such as division by zero are reported at.
+    BinaryOp(Rc<AstNodes<I>>, Op, Rc<AstNodes<I>>, Span),
+    UnaryOp(Op, Rc<AstNodes<I>>),
+    #[cfg_attr(feature = "serde", serde(with = "ibig_string"))]
     Number(IBig),
-    VarDef(usize, Rc<AstNodes>),
-    ConstDef(usize, Rc<AstNodes>),
-    ReadVar(usize),
-    FunctionDef(usize, Vec<usize>, Vec<AstNodes>),
-    Call(usize, Vec<AstNodes>),
-    Return(Rc<AstNodes>),
-    If(Rc<AstNodes>, Vec<AstNodes>, Vec<AstNodes>),
+    Float(f64),
+    Bool(bool),
+    /// A string literal, holding the id of its text with escapes already replaced. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::CrValue;
+    ///
+    /// let value = cara::eval(r#""a\nb\t\\\"""#).unwrap();
+    /// assert_eq!(value, CrValue::Str("a\nb\t\\\"".into()));
+    /// assert_eq!(cara::eval(r#""""#).unwrap(), CrValue::Str("".into()));
+    ///
+    /// // `len` counts characters, not bytes.
+    /// let value = cara::eval(r#"[len(""), len("abc"), len("héllo"), len("日本")]"#).unwrap();
+    /// assert_eq!(value, cara::eval("[0, 3, 5, 2]").unwrap());
+    /// ```
+    Str(I),
+    Char(char),
+    VarDef(I, Rc<AstNodes<I>>),
+    /// Variables defined with the elements of a list, like `var a, b = 1, 2;`. \
+    /// Example
+    /// ```rust
+    /// let value = cara::eval("var q, r = 17 / 5, 17 % 5; [q, r]").unwrap();
+    /// assert_eq!(value, cara::eval("[3, 2]").unwrap());
+    /// ```
+    VarUnpack(Vec<I>, Rc<AstNodes<I>>),
+    ConstDef(I, Rc<AstNodes<I>>),
+    ReadVar(I),
+    /// A named function with its parameters and body. It sees the globals and the
+    /// locals around its definition, but not the locals of whoever calls it. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::{CrValue, Error};
+    /// use cara::CaraError;
+    ///
+    /// let src = "
+    ///     fn outer() {
+    ///         fn fact(n) { if n == 0 { return 1; } return n * fact(n - 1); }
+    ///         return fact(5);
+    ///     }
+    ///     outer()
+    /// ";
+    /// assert_eq!(cara::eval(src).unwrap(), CrValue::Number(120.into()));
+    /// let src = "if true { fn g(n) { if n == 0 { return 0; } return g(n - 1); } g(3) }";
+    /// assert_eq!(cara::eval(src).unwrap(), CrValue::Number(0.into()));
+    ///
+    /// let src = "fn f() { return x; } fn caller() { var x = 1; return f(); } caller()";
+    /// let error = cara::eval(src).unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::SymbolNotFound(name)) if name == "x"));
+    /// ```
+    FunctionDef(I, Vec<I>, Vec<AstNodes<I>>),
+    /// An anonymous function, which captures the variables around it like a named
+    /// one. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::CrValue;
+    ///
+    /// let src = "
+    ///     fn adder(n) { return fn(x) { return x + n; }; }
+    ///     var add_ten = adder(10);
+    ///     add_ten(5)
+    /// ";
+    /// assert_eq!(cara::eval(src).unwrap(), CrValue::Number(15.into()));
+    /// let value = cara::eval("map([1, 2, 3], fn(x) { return x * x; })").unwrap();
+    /// assert_eq!(value, cara::eval("[1, 4, 9]").unwrap());
+    /// ```
+    Lambda(Vec<I>, Vec<AstNodes<I>>),
+    /// A call of the function the first node evaluates to. Calling a variable by name
+    /// calls the builtin with that name if there is one. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::{CrValue, Error};
+    /// use cara::CaraError;
+    ///
+    /// let src = "
+    ///     fn adder(n) { return fn(x) { return x + n; }; }
+    ///     fn get_fn() { return fn() { return 7; }; }
+    ///     [adder(10)(5), get_fn()(), fn(x) { return x * 2; }(4)]
+    /// ";
+    /// assert_eq!(cara::eval(src).unwrap(), cara::eval("[15, 7, 8]").unwrap());
+    ///
+    /// let src = "
+    ///     var fns = [fn(x) { return x + 1; }, fn(x) { return [x, x * 2]; }];
+    ///     [fns[0](1), fns[1](3)[1], [1, 2, 3][1]]
+    /// ";
+    /// assert_eq!(cara::eval(src).unwrap(), cara::eval("[2, 6, 2]").unwrap());
+    /// let error = cara::eval("var fns = [1]; fns[0](1)").unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::NotCallable(_))));
+    /// ```
+    Call(Rc<AstNodes<I>>, Vec<AstNodes<I>>),
+    /// A `return`, with no value for a bare `return;`, which returns void like
+    /// falling off the end of the function. \
+    /// Example
+    /// ```rust
+    /// use cara::ast::AstNodes;
+    /// use cara::backend::CrValue;
+    /// use cara::frontend::{Lexer, Parser};
+    ///
+    /// let lexer = Lexer::new("return;".into());
+    /// let (ast, _) = Parser::new(lexer).parse_compile_unit().unwrap();
+    /// let AstNodes::CompileUnit(nodes) = ast else { unreachable!() };
+    /// assert!(matches!(nodes.as_slice(), [AstNodes::Return(None)]));
+    ///
+    /// let src = "fn f(x) { if x > 0 { return; } 1 } f(1)";
+    /// assert_eq!(cara::eval(src).unwrap(), CrValue::Void);
+    ///
+    /// // A function returning a call to itself runs in a loop, so it can go deep.
+    /// let src = "fn sum(n, acc) { if n == 0 { return acc; } return sum(n - 1, acc + n); } sum(100000, 0)";
+    /// assert_eq!(cara::eval(src).unwrap(), CrValue::Number(5000050000u64.into()));
+    /// ```
+    Return(Option<Rc<AstNodes<I>>>),
+    /// A condition and the blocks run when it holds and when it doesn't. `else if`
+    /// and `elif` put another `If` alone in the else block. \
+    /// Example
+    /// ```rust
+    /// use cara::ast::AstNodes;
+    /// use cara::backend::CrValue;
+    /// use cara::frontend::{Lexer, Parser};
+    ///
+    /// let src = "if a { 1 } else if b { 2 } else { 3 }";
+    /// let (ast, _) = Parser::new(Lexer::new(src.into())).parse_compile_unit().unwrap();
+    /// let AstNodes::CompileUnit(nodes) = ast else { unreachable!() };
+    /// let AstNodes::If(_, _, else_block) = &nodes[0] else { unreachable!() };
+    /// let [AstNodes::If(_, then_block, else_block)] = else_block.as_slice() else {
+    ///     unreachable!()
+    /// };
+    /// assert!(matches!(then_block.as_slice(), [AstNodes::Number(_)]));
+    /// assert!(matches!(else_block.as_slice(), [AstNodes::Number(_)]));
+    ///
+    /// let src = "
+    ///     fn grade(n) {
+    ///         if n >= 90 { return 1; } else if n >= 50 { return 2; } else { return 3; }
+    ///     }
+    ///     [grade(95), grade(60), grade(10)]
+    /// ";
+    /// assert_eq!(cara::eval(src).unwrap(), cara::eval("[1, 2, 3]").unwrap());
+    ///
+    /// let src = "
+    ///     fn sign(n) {
+    ///         if n < 0 { return -1; } elif n == 0 { return 0; } else { return 1; }
+    ///     }
+    ///     [sign(-5), sign(0), sign(7)]
+    /// ";
+    /// assert_eq!(cara::eval(src).unwrap(), cara::eval("[-1, 0, 1]").unwrap());
+    /// ```
+    If(Rc<AstNodes<I>>, Vec<AstNodes<I>>, Vec<AstNodes<I>>),
+    /// `condition ? then : else`, which only evaluates the value it gives. Nested
+    /// ternaries group from the right. \
+    /// Example
+    /// ```rust
+    /// use cara::ast::AstNodes;
+    /// use cara::backend::CrValue;
+    /// use cara::frontend::{Lexer, Parser};
+    ///
+    /// let lexer = Lexer::new("a ? b : c ? d : e".into());
+    /// let (ast, _) = Parser::new(lexer).parse_compile_unit().unwrap();
+    /// let AstNodes::CompileUnit(nodes) = ast else { unreachable!() };
+    /// let AstNodes::Ternary(_, _, else_value) = &nodes[0] else { unreachable!() };
+    /// assert!(matches!(else_value.as_ref(), AstNodes::Ternary(..)));
+    ///
+    /// let value = cara::eval("var x = 3; var y = 5; var m = x > y ? x : y; m").unwrap();
+    /// assert_eq!(value, CrValue::Number(5.into()));
+    /// let src = "fn sign(n) { return n < 0 ? -1 : n == 0 ? 0 : 1; } [sign(-4), sign(0), sign(9)]";
+    /// assert_eq!(cara::eval(src).unwrap(), cara::eval("[-1, 0, 1]").unwrap());
+    /// // The branch not taken isn't evaluated.
+    /// assert_eq!(cara::eval("true ? 1 : 1 / 0").unwrap(), CrValue::Number(1.into()));
+    /// ```
+    Ternary(Rc<AstNodes<I>>, Rc<AstNodes<I>>, Rc<AstNodes<I>>),
+    /// A loop over a range of numbers, holding the variable, start, end, step and
+    /// body. The variable is bound afresh on every pass, so the body may change it
+    /// without changing which numbers the loop goes through. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::CrValue;
+    ///
+    /// let output = cara::run_and_capture("for i in (0, 3) { i = i * 10; print(i); }");
+    /// assert_eq!(output.unwrap(), "0\n10\n20\n");
+    ///
+    /// // Like `range`, the step may be negative and the bounds may be any size.
+    /// let output = cara::run_and_capture("for i in (10, 0, -3) { write(i, \"\"); }");
+    /// assert_eq!(output.unwrap(), "10 7 4 1 ");
+    /// let src = "var n = 0; for i in (10 ** 30, 10 ** 30 + 3) { n = n + 1; } n";
+    /// assert_eq!(cara::eval(src).unwrap(), CrValue::Number(3.into()));
+    /// ```
     For(
-        usize,
-        Rc<AstNodes>,
-        Rc<AstNodes>,
-        Rc<AstNodes>,
-        Vec<AstNodes>,
+        I,
+        Rc<AstNodes<I>>,
+        Rc<AstNodes<I>>,
+        Rc<AstNodes<I>>,
+        Vec<AstNodes<I>>,
     ),
-    List(Vec<AstNodes>),
-    TemplateList(Rc<AstNodes>, Rc<AstNodes>),
-    Index(usize, Rc<AstNodes>),
-    While(Rc<AstNodes>, Vec<AstNodes>),
-    Break,
+    /// A loop over the elements of a list, like `for x in xs { ... }`. A range in
+    /// parentheses makes a [`AstNodes::For`] instead. \
+    /// Example
+    /// ```rust
+    /// use cara::ast::AstNodes;
+    /// use cara::frontend::{Lexer, Parser};
+    ///
+    /// let lexer = Lexer::new("for x in xs { print(x); }".into());
+    /// let (ast, _) = Parser::new(lexer).parse_compile_unit().unwrap();
+    /// let AstNodes::CompileUnit(nodes) = ast else { unreachable!() };
+    /// assert!(matches!(nodes[0], AstNodes::ForEach(..)));
+    ///
+    /// let output = cara::run_and_capture("for x in [3, 1, 2] { write(x * 10, \"\"); }");
+    /// assert_eq!(output.unwrap(), "30 10 20 ");
+    /// let output = cara::run_and_capture("for x in [] { print(x); }");
+    /// assert_eq!(output.unwrap(), "");
+    ///
+    /// // A list which only starts with parentheses isn't a range.
+    /// let lexer = Lexer::new("for x in ([1, 2]) + [3] { print(x); }".into());
+    /// let (ast, _) = Parser::new(lexer).parse_compile_unit().unwrap();
+    /// let AstNodes::CompileUnit(nodes) = ast else { unreachable!() };
+    /// let AstNodes::ForEach(_, list, _) = &nodes[0] else { unreachable!() };
+    /// assert!(matches!(list.as_ref(), AstNodes::BinaryOp(..)));
+    ///
+    /// let src = "var xs = [[1, 2]]; for x in (xs)[0] { write(x, \"\"); } for x in ([1]) + [2] { write(x, \"\"); }";
+    /// assert_eq!(cara::run_and_capture(src).unwrap(), "1 2 1 2 ");
+    /// ```
+    ForEach(I, Rc<AstNodes<I>>, Vec<AstNodes<I>>),
+    List(Vec<AstNodes<I>>),
+    /// A map literal, holding the key and value of every entry. \
+    /// Example
+    /// ```rust
+    /// use cara::ast::AstNodes;
+    /// use cara::frontend::{Lexer, Parser};
+    ///
+    /// let lexer = Lexer::new(r#"var m = {"a": 1, 'b': 1 + 1};"#.into());
+    /// let (ast, _) = Parser::new(lexer).parse_compile_unit().unwrap();
+    /// let AstNodes::CompileUnit(nodes) = ast else { unreachable!() };
+    /// let AstNodes::VarDef(_, value) = &nodes[0] else { unreachable!() };
+    /// let AstNodes::Map(entries) = value.as_ref() else { unreachable!() };
+    /// assert!(matches!(entries[1], (AstNodes::Char('b'), AstNodes::BinaryOp(..))));
+    /// ```
+    Map(Vec<(AstNodes<I>, AstNodes<I>)>),
+    TemplateList(Rc<AstNodes<I>>, Rc<AstNodes<I>>),
+    /// A list and the index of the element read from it, so `m[1][0]` indexes the
+    /// result of `m[1]`. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::{CrValue, Error};
+    /// use cara::CaraError;
+    ///
+    /// let value = cara::eval("var m = [[1, 2], [3, 4]]; m[1][0]").unwrap();
+    /// assert_eq!(value, CrValue::Number(3.into()));
+    /// let value = cara::eval("var c = [[[1], [2]], [[3], [4, 5]]]; c[1][1][1]").unwrap();
+    /// assert_eq!(value, CrValue::Number(5.into()));
+    /// let error = cara::eval("var m = [[1, 2], [3, 4]]; m[1][2]").unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::IndexOutOfRange)));
+    /// ```
+    Index(Rc<AstNodes<I>>, Rc<AstNodes<I>>, Span),
+    /// A loop which runs while the condition holds. Like the other loops, it evaluates
+    /// to the value given to `break`, or void if it ends otherwise. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::CrValue;
+    ///
+    /// let src = "var i = 0; var found = while i < 10 { i = i + 1; if i % 7 == 0 { break 7; } }; found";
+    /// assert_eq!(cara::eval(src).unwrap(), CrValue::Number(7.into()));
+    /// let src = "var i = 0; var found = while i < 3 { i = i + 1; }; found";
+    /// assert_eq!(cara::eval(src).unwrap(), CrValue::Void);
+    /// let src = "var x = for i in (0, 10) { if i * i > 20 { break i; } }; x";
+    /// assert_eq!(cara::eval(src).unwrap(), CrValue::Number(5.into()));
+    /// ```
+    While(Rc<AstNodes<I>>, Vec<AstNodes<I>>),
+    /// A loop which only ends with `break`, and evaluates to the value given to it. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::CrValue;
+    ///
+    /// let src = "var i = 0; var x = loop { i = i + 1; if i * i > 50 { break i; } }; x";
+    /// assert_eq!(cara::eval(src).unwrap(), CrValue::Number(8.into()));
+    /// let src = "var i = 0; var x = loop { i = i + 1; if i == 3 { break; } }; x";
+    /// assert_eq!(cara::eval(src).unwrap(), CrValue::Void);
+    /// ```
+    Loop(Vec<AstNodes<I>>),
+    Break(Option<Rc<AstNodes<I>>>),
+    /// Skips to the next pass of the innermost loop. Like `break`, it leaves any `if`
+    /// blocks it is nested in on the way. \
+    /// Example
+    /// ```rust
+    /// let src = "
+    ///     var out = [];
+    ///     for i in (0, 10) {
+    ///         if i % 2 == 0 { if i < 6 { continue; } else { break; } }
+    ///         push(out, i);
+    ///     }
+    ///     out
+    /// ";
+    /// assert_eq!(cara::eval(src).unwrap(), cara::eval("[1, 3, 5]").unwrap());
+    /// ```
     Continue,
 }
+
+impl<I> AstNodes<I> {
+    /// Returns the name of the node's variant, such as `"BinaryOp"`.
+    #[must_use]
+    pub const fn kind(&self) -> &'static str {
+        match self {
+            Self::Assign(..) => "Assign",
+            Self::Unpack(..) => "Unpack",
+            Self::CompileUnit(_) => "CompileUnit",
+            Self::BinaryOp(..) => "BinaryOp",
+            Self::UnaryOp(..) => "UnaryOp",
+            Self::Number(_) => "Number",
+            Self::Float(_) => "Float",
+            Self::Bool(_) => "Bool",
+            Self::Str(_) => "Str",
+            Self::Char(_) => "Char",
+            Self::VarDef(..) => "VarDef",
+            Self::VarUnpack(..) => "VarUnpack",
+            Self::ConstDef(..) => "ConstDef",
+            Self::ReadVar(_) => "ReadVar",
+            Self::FunctionDef(..) => "FunctionDef",
+            Self::Lambda(..) => "Lambda",
+            Self::Call(..) => "Call",
+            Self::Return(_) => "Return",
+            Self::If(..) => "If",
+            Self::Ternary(..) => "Ternary",
+            Self::For(..) => "For",
+            Self::ForEach(..) => "ForEach",
+            Self::List(_) => "List",
+            Self::Map(_) => "Map",
+            Self::TemplateList(..) => "TemplateList",
+            Self::Index(..) => "Index",
+            Self::While(..) => "While",
+            Self::Loop(_) => "Loop",
+            Self::Break(_) => "Break",
+            Self::Continue => "Continue",
+        }
+    }
+}
+
+impl AstNodes {
+    /// Returns a copy of the AST with every interned id replaced by `f(id)`.
+    pub fn map_ids<I>(&self, f: &mut impl FnMut(usize) -> I) -> AstNodes<I> {
+        fn map_all<I>(nodes: &[AstNodes], f: &mut impl FnMut(usize) -> I) -> Vec<AstNodes<I>> {
+            nodes.iter().map(|node| node.map_ids(f)).collect()
+        }
+
+        match self {
+            Self::Assign(id, index, value) => {
+                AstNodes::Assign(f(*id), map_all(index, f), value.map_ids(f).into())
+            }
+            Self::Unpack(ids, value) => AstNodes::Unpack(
+                ids.iter().map(|id| f(*id)).collect(),
+                value.map_ids(f).into(),
+            ),
+            Self::CompileUnit(nodes) => AstNodes::CompileUnit(map_all(nodes, f)),
+            Self::BinaryOp(left, op, right, span) => {
+                AstNodes::BinaryOp(left.map_ids(f).into(), *op, right.map_ids(f).into(), *span)
+            }
+            Self::UnaryOp(op, value) => AstNodes::UnaryOp(*op, value.map_ids(f).into()),
+            Self::Number(number) => AstNodes::Number(number.clone()),
+            Self::Float(value) => AstNodes::Float(*value),
+            Self::Bool(value) => AstNodes::Bool(*value),
+            Self::Str(id) => AstNodes::Str(f(*id)),
+            Self::Char(ch) => AstNodes::Char(*ch),
+            Self::VarDef(id, value) => AstNodes::VarDef(f(*id), value.map_ids(f).into()),
+            Self::VarUnpack(ids, value) => AstNodes::VarUnpack(
+                ids.iter().map(|id| f(*id)).collect(),
+                value.map_ids(f).into(),
+            ),
+            Self::ConstDef(id, value) => AstNodes::ConstDef(f(*id), value.map_ids(f).into()),
+            Self::ReadVar(id) => AstNodes::ReadVar(f(*id)),
+            Self::FunctionDef(id, params, body) => AstNodes::FunctionDef(
+                f(*id),
+                params.iter().map(|param| f(*param)).collect(),
+                map_all(body, f),
+            ),
+            Self::Lambda(params, body) => AstNodes::Lambda(
+                params.iter().map(|param| f(*param)).collect(),
+                map_all(body, f),
+            ),
+            Self::Call(callee, args) => AstNodes::Call(callee.map_ids(f).into(), map_all(args, f)),
+            Self::Return(value) => {
+                AstNodes::Return(value.as_ref().map(|value| value.map_ids(f).into()))
+            }
+            Self::If(condition, then_block, else_block) => AstNodes::If(
+                condition.map_ids(f).into(),
+                map_all(then_block, f),
+                map_all(else_block, f),
+            ),
+            Self::Ternary(condition, then_value, else_value) => AstNodes::Ternary(
+                condition.map_ids(f).into(),
+                then_value.map_ids(f).into(),
+                else_value.map_ids(f).into(),
+            ),
+            Self::For(variable, start, end, step, body) => AstNodes::For(
+                f(*variable),
+                start.map_ids(f).into(),
+                end.map_ids(f).into(),
+                step.map_ids(f).into(),
+                map_all(body, f),
+            ),
+            Self::ForEach(variable, list, body) => {
+                AstNodes::ForEach(f(*variable), list.map_ids(f).into(), map_all(body, f))
+            }
+            Self::List(values) => AstNodes::List(map_all(values, f)),
+            Self::Map(entries) => AstNodes::Map(
+                entries
+                    .iter()
+                    .map(|(key, value)| (key.map_ids(f), value.map_ids(f)))
+                    .collect(),
+            ),
+            Self::TemplateList(template, size) => {
+                AstNodes::TemplateList(template.map_ids(f).into(), size.map_ids(f).into())
+            }
+            Self::Index(list, index, span) => {
+                AstNodes::Index(list.map_ids(f).into(), index.map_ids(f).into(), *span)
+            }
+            Self::While(condition, body) => {
+                AstNodes::While(condition.map_ids(f).into(), map_all(body, f))
+            }
+            Self::Loop(body) => AstNodes::Loop(map_all(body, f)),
+            Self::Break(value) => {
+                AstNodes::Break(value.as_ref().map(|value| value.map_ids(f).into()))
+            }
+            Self::Continue => AstNodes::Continue,
+        }
+    }
+}
+
+/// Calls `f` on `node` and then on every node below it, parents before children. \
+/// Example
+/// ```rust
+/// use cara::ast::{visit_nodes, AstNodes};
+/// use cara::frontend::{Lexer, Parser};
+///
+/// let lexer = Lexer::new("var a = 1 + 2 * 3; print(a - 1);".into());
+/// let (ast, _) = Parser::new(lexer).parse_compile_unit().unwrap();
+///
+/// let mut count = 0;
+/// visit_nodes(&ast, &mut |node| {
+///     if let AstNodes::BinaryOp(..) = node {
+///         count += 1;
+///     }
+/// });
+/// assert_eq!(count, 3);
+/// ```
+pub fn visit_nodes(node: &AstNodes, f: &mut dyn FnMut(&AstNodes)) {
+    f(node);
+    match node {
+        AstNodes::Number(_)
+        | AstNodes::Float(_)
+        | AstNodes::Bool(_)
+        | AstNodes::Str(_)
+        | AstNodes::Char(_)
+        | AstNodes::ReadVar(_)
+        | AstNodes::Return(None)
+        | AstNodes::Break(None)
+        | AstNodes::Continue => {}
+        AstNodes::Assign(_, indices, value) => {
+            indices.iter().for_each(|index| visit_nodes(index, f));
+            visit_nodes(value, f);
+        }
+        AstNodes::BinaryOp(left, _, right, _)
+        | AstNodes::TemplateList(left, right)
+        | AstNodes::Index(left, right, _) => {
+            visit_nodes(left, f);
+            visit_nodes(right, f);
+        }
+        AstNodes::UnaryOp(_, value)
+        | AstNodes::VarDef(_, value)
+        | AstNodes::Unpack(_, value)
+        | AstNodes::VarUnpack(_, value)
+        | AstNodes::ConstDef(_, value)
+        | AstNodes::Return(Some(value))
+        | AstNodes::Break(Some(value)) => visit_nodes(value, f),
+        AstNodes::CompileUnit(nodes)
+        | AstNodes::FunctionDef(_, _, nodes)
+        | AstNodes::Lambda(_, nodes)
+        | AstNodes::Loop(nodes)
+        | AstNodes::List(nodes) => nodes.iter().for_each(|node| visit_nodes(node, f)),
+        AstNodes::Call(callee, args) => {
+            visit_nodes(callee, f);
+            args.iter().for_each(|arg| visit_nodes(arg, f));
+        }
+        AstNodes::If(condition, then_block, else_block) => {
+            visit_nodes(condition, f);
+            then_block.iter().for_each(|node| visit_nodes(node, f));
+            else_block.iter().for_each(|node| visit_nodes(node, f));
+        }
+        AstNodes::Ternary(condition, then_value, else_value) => {
+            visit_nodes(condition, f);
+            visit_nodes(then_value, f);
+            visit_nodes(else_value, f);
+        }
+        AstNodes::For(_, start, end, step, body) => {
+            visit_nodes(start, f);
+            visit_nodes(end, f);
+            visit_nodes(step, f);
+            body.iter().for_each(|node| visit_nodes(node, f));
+        }
+        AstNodes::While(condition, body) | AstNodes::ForEach(_, condition, body) => {
+            visit_nodes(condition, f);
+            body.iter().for_each(|node| visit_nodes(node, f));
+        }
+        AstNodes::Map(entries) => {
+            for (key, value) in entries {
+                visit_nodes(key, f);
+                visit_nodes(value, f);
+            }
+        }
+    }
+}
+
+/// Rebuilds `node` from the bottom up, calling `f` on every node once its children
+/// have been rebuilt, so a pass only has to handle the nodes it changes. \
+/// Example
+/// ```rust
+/// use cara::ast::{transform_nodes, AstNodes};
+/// use cara::frontend::{Lexer, Parser};
+///
+/// let lexer = Lexer::new("var a = [1, true]; print(a, false);".into());
+/// let (ast, _) = Parser::new(lexer).parse_compile_unit().unwrap();
+///
+/// // Replaces every bool with a number.
+/// let ast = transform_nodes(ast, &mut |node| match node {
+///     AstNodes::Bool(value) => AstNodes::Number(u8::from(value).into()),
+///     node => node,
+/// });
+/// let mut bools = 0;
+/// cara::ast::visit_nodes(&ast, &mut |node| bools += usize::from(node.kind() == "Bool"));
+/// assert_eq!(bools, 0);
+/// ```
+pub fn transform_nodes(node: AstNodes, f: &mut dyn FnMut(AstNodes) -> AstNodes) -> AstNodes {
+    fn one(node: Rc<AstNodes>, f: &mut dyn FnMut(AstNodes) -> AstNodes) -> Rc<AstNodes> {
+        transform_nodes(Rc::unwrap_or_clone(node), f).into()
+    }
+
+    fn all(nodes: Vec<AstNodes>, f: &mut dyn FnMut(AstNodes) -> AstNodes) -> Vec<AstNodes> {
+        nodes
+            .into_iter()
+            .map(|node| transform_nodes(node, f))
+            .collect()
+    }
+
+    let node = match node {
+        AstNodes::Number(_)
+        | AstNodes::Float(_)
+        | AstNodes::Bool(_)
+        | AstNodes::Str(_)
+        | AstNodes::Char(_)
+        | AstNodes::ReadVar(_)
+        | AstNodes::Return(None)
+        | AstNodes::Break(None)
+        | AstNodes::Continue => node,
+        AstNodes::Assign(id, indices, value) => {
+            AstNodes::Assign(id, all(indices, f), one(value, f))
+        }
+        AstNodes::CompileUnit(nodes) => AstNodes::CompileUnit(all(nodes, f)),
+        AstNodes::BinaryOp(left, op, right, span) => {
+            AstNodes::BinaryOp(one(left, f), op, one(right, f), span)
+        }
+        AstNodes::UnaryOp(op, value) => AstNodes::UnaryOp(op, one(value, f)),
+        AstNodes::Unpack(ids, value) => AstNodes::Unpack(ids, one(value, f)),
+        AstNodes::VarDef(id, value) => AstNodes::VarDef(id, one(value, f)),
+        AstNodes::VarUnpack(ids, value) => AstNodes::VarUnpack(ids, one(value, f)),
+        AstNodes::ConstDef(id, value) => AstNodes::ConstDef(id, one(value, f)),
+        AstNodes::FunctionDef(id, params, body) => AstNodes::FunctionDef(id, params, all(body, f)),
+        AstNodes::Lambda(params, body) => AstNodes::Lambda(params, all(body, f)),
+        AstNodes::Call(callee, args) => AstNodes::Call(one(callee, f), all(args, f)),
+        AstNodes::Return(Some(value)) => AstNodes::Return(Some(one(value, f))),
+        AstNodes::If(condition, then_block, else_block) => {
+            AstNodes::If(one(condition, f), all(then_block, f), all(else_block, f))
+        }
+        AstNodes::Ternary(condition, then_value, else_value) => {
+            AstNodes::Ternary(one(condition, f), one(then_value, f), one(else_value, f))
+        }
+        AstNodes::For(variable, start, end, step, body) => AstNodes::For(
+            variable,
+            one(start, f),
+            one(end, f),
+            one(step, f),
+            all(body, f),
+        ),
+        AstNodes::ForEach(variable, list, body) => {
+            AstNodes::ForEach(variable, one(list, f), all(body, f))
+        }
+        AstNodes::List(values) => AstNodes::List(all(values, f)),
+        AstNodes::Map(entries) => AstNodes::Map(
+            entries
+                .into_iter()
+                .map(|(key, value)| (transform_nodes(key, f), transform_nodes(value, f)))
+                .collect(),
+        ),
+        AstNodes::TemplateList(template, size) => {
+            AstNodes::TemplateList(one(template, f), one(size, f))
+        }
+        AstNodes::Index(list, index, span) => AstNodes::Index(one(list, f), one(index, f), span),
+        AstNodes::While(condition, body) => AstNodes::While(one(condition, f), all(body, f)),
+        AstNodes::Loop(body) => AstNodes::Loop(all(body, f)),
+        AstNodes::Break(Some(value)) => AstNodes::Break(Some(one(value, f))),
+    };
+    f(node)
+}
+
+/// Serializes big integers in their decimal string form, which any format can hold.
+#[cfg(feature = "serde")]
+mod ibig_string {
+    use alloc::string::{String, ToString};
+    use dashu_int::IBig;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(number: &IBig, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&number.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<IBig, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        IBig::from_str_radix(&text, 10).map_err(D::Error::custom)
+    }
+}
@@ -1,14 +1,42 @@
-use core::fmt;
+use alloc::collections::BTreeMap;
+use alloc::{format, vec};
+use alloc::{
+    rc::Rc,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::cell::RefCell;
+use core::cmp::Ordering;
+use core::fmt::{self, Write};
+use core::iter::zip;
+use dashu_int::fast_div::ConstDivisor;
+use dashu_int::ops::{Abs, BitTest, Gcd, UnsignedAbs};
 use dashu_int::IBig;
 use spin::Mutex;
 
 use super::result::{Error, Result};
-use super::value::CrValue;
+use super::value::{list_index, CrValue};
 use super::Interpreter;
 use crate::ast::AstNodes;
 
 static PRINTER: Mutex<Option<fn(fmt::Arguments)>> = Mutex::new(None);
 
+/// Sets the function which receives everything scripts print. \
+/// Example
+/// ```rust
+/// use std::fmt::{self, Write};
+/// use std::sync::Mutex;
+///
+/// static OUTPUT: Mutex<String> = Mutex::new(String::new());
+///
+/// fn capture(args: fmt::Arguments) {
+///     OUTPUT.lock().unwrap().write_fmt(args).unwrap();
+/// }
+///
+/// cara::backend::set_printer(capture);
+/// cara::eval(r#"print(1, "a"); print(); println(2); write(3, 4); print(true);"#).unwrap();
+/// assert_eq!(*OUTPUT.lock().unwrap(), "1 a\n\n2\n3 4true\n");
+/// ```
 pub fn set_printer(printer: fn(fmt::Arguments)) {
     let mut log = PRINTER.lock();
     *log = Some(printer);
@@ -21,14 +49,128 @@ pub fn print_message(args: fmt::Arguments) {
 }
 
 impl Interpreter {
-    pub(super) fn print(&mut self, args: &[AstNodes]) -> Result<()> {
-        for arg in args {
-            print_message(format_args!("{}", self.visit(arg)?));
+    /// Prints the arguments separated by spaces, followed by a newline if `newline` is set.
+    /// `print` and `println` end with a newline and `write` doesn't. \
+    /// Example
+    /// ```rust
+    /// use std::cell::RefCell;
+    /// use std::fmt::Write;
+    /// use std::rc::Rc;
+    /// use cara::backend::{Error, Interpreter};
+    /// use cara::frontend::{Lexer, Parser};
+    ///
+    /// assert_eq!(cara::run_and_capture("print();").unwrap(), "\n");
+    /// assert_eq!(cara::run_and_capture("print(1);").unwrap(), "1\n");
+    /// assert_eq!(cara::run_and_capture("print(1, [2]);").unwrap(), "1 [2,]\n");
+    /// assert_eq!(cara::run_and_capture(r#"println(1, "a", true);"#).unwrap(), "1 a true\n");
+    /// assert_eq!(cara::run_and_capture("write();").unwrap(), "");
+    /// assert_eq!(cara::run_and_capture("write(1, 2); write(3);").unwrap(), "1 23");
+    ///
+    /// // Nothing is printed if an argument fails.
+    /// let lexer = Lexer::new(r#"print(1, int("x"));"#.into());
+    /// let (ast, strings) = Parser::new(lexer).parse_compile_unit().unwrap();
+    /// let output = Rc::new(RefCell::new(String::new()));
+    /// let buffer = output.clone();
+    /// let mut interpreter = Interpreter::new(strings);
+    /// interpreter.set_printer(Box::new(move |args| buffer.borrow_mut().write_fmt(args).unwrap()));
+    /// assert!(matches!(interpreter.visit(&ast), Err(Error::ParseInt(_))));
+    /// assert_eq!(*output.borrow(), "");
+    /// ```
+    pub(super) fn print(&mut self, args: &[AstNodes], newline: bool) -> Result<()> {
+        let values = args
+            .iter()
+            .map(|arg| self.visit(arg))
+            .collect::<Result<Vec<CrValue>>>()?;
+        let mut output = String::new();
+        for (i, value) in values.iter().enumerate() {
+            let separator = if i == 0 { "" } else { " " };
+            // Writing to a string can't fail.
+            let _ = write!(
+                output,
+                "{separator}{}",
+                value.display(self.max_display_digits)
+            );
         }
-        print_message(format_args!("\n"));
+        if newline {
+            output.push('\n');
+        }
+        self.write_output(format_args!("{output}"));
         Ok(())
     }
 
+    /// Prints the arguments after the first formatted by it, which holds `%d`, `%x` or
+    /// `%b` for a number in decimal, hexadecimal or binary, `%s` for any value and `%%`
+    /// for a percent sign. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::Error;
+    /// use cara::CaraError;
+    ///
+    /// let output = cara::run_and_capture(r#"printf("%d %x %b\n", 10, 255, 5);"#).unwrap();
+    /// assert_eq!(output, "10 ff 101\n");
+    /// let output = cara::run_and_capture(r#"printf("%s: 100%%", [1, 2]);"#).unwrap();
+    /// assert_eq!(output, "[1,2,]: 100%");
+    ///
+    /// let error = cara::run_and_capture(r#"printf("%d %d", 1);"#).unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::ArgMismatch)));
+    /// assert!(cara::run_and_capture(r#"printf("%d", 1, 2);"#).is_err());
+    /// assert!(cara::run_and_capture(r#"printf("%x", "a");"#).is_err());
+    /// ```
+    pub(super) fn printf(&mut self, args: &[AstNodes]) -> Result<()> {
+        let Some((format, args)) = args.split_first() else {
+            return Err(Error::ArgMismatch);
+        };
+        let CrValue::Str(format) = self.visit(format)? else {
+            return Err(Error::ArgMismatch);
+        };
+        let mut args = args.iter();
+        let mut output = String::new();
+        let mut chars = format.chars();
+        // The output is collected first so nothing is printed if the format is wrong.
+        while let Some(ch) = chars.next() {
+            if ch != '%' {
+                output.push(ch);
+                continue;
+            }
+            let spec = chars.next().ok_or(Error::ArgMismatch)?;
+            if spec == '%' {
+                output.push('%');
+                continue;
+            }
+            let value = self.visit(args.next().ok_or(Error::ArgMismatch)?)?;
+            // Writing to a string can't fail.
+            let _ = match (spec, &value) {
+                ('d', CrValue::Number(number)) => write!(output, "{number}"),
+                ('x', CrValue::Number(number)) => write!(output, "{number:x}"),
+                ('b', CrValue::Number(number)) => write!(output, "{number:b}"),
+                ('s', value) => write!(output, "{}", value.display(self.max_display_digits)),
+                _ => return Err(Error::ArgMismatch),
+            };
+        }
+        if args.next().is_some() {
+            return Err(Error::ArgMismatch);
+        }
+        self.write_output(format_args!("{output}"));
+        Ok(())
+    }
+
+    /// Sends printed text to this interpreter's printer, or to the global one
+    /// if it doesn't have its own.
+    fn write_output(&mut self, args: fmt::Arguments) {
+        match &mut self.printer {
+            Some(printer) => printer(args),
+            None => print_message(args),
+        }
+    }
+
+    pub(super) fn input(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        if !args.is_empty() {
+            return Err(Error::ArgMismatch);
+        }
+        let reader = self.reader.as_mut().ok_or(Error::NoReader)?;
+        Ok(reader().map_or(CrValue::Void, CrValue::Str))
+    }
+
     pub(super) fn append(&mut self, args: &[AstNodes]) -> Result<()> {
         if args.len() != 2 {
             return Err(Error::ArgMismatch);
@@ -38,7 +180,7 @@ impl Interpreter {
             self.symbol_tables.symbol_list_append(id, value)?;
             Ok(())
         } else {
-            Err(Error::ArgMismatch)
+            Err(Error::NotAssignable)
         }
     }
 
@@ -48,43 +190,914 @@ impl Interpreter {
         }
         if let AstNodes::ReadVar(id) = args[0] {
             let number = self.visit(&args[1])?;
-            let index = usize::try_from(number.as_int()?).unwrap();
+            let index = list_index(number.as_int()?)?;
             let value = self.visit(&args[2])?;
 
             self.symbol_tables.symbol_list_insert(id, index, value)?;
 
             Ok(())
         } else {
-            Err(Error::ArgMismatch)
+            Err(Error::NotAssignable)
         }
     }
 
-    pub(super) fn len(&self, args: &[AstNodes]) -> Result<CrValue> {
-        if args.len() != 1 {
+    /// Returns the value of a map at a key, or the third argument if there is one and
+    /// the map doesn't have the key. \
+    /// Example
+    /// ```rust
+    /// let src = r#"
+    ///     var m = {"a": 1};
+    ///     set(m, "b", 2);
+    ///     [get(m, "a"), get(m, "b"), get(m, "c", 0), keys(m), values(m), len(m)]
+    /// "#;
+    /// let expected = cara::eval(r#"[1, 2, 0, ["a", "b"], [1, 2], 2]"#).unwrap();
+    /// assert_eq!(cara::eval(src).unwrap(), expected);
+    /// assert!(cara::eval(r#"get({"a": 1}, "b")"#).is_err());
+    /// assert!(cara::eval(r#"var m = {}; set(m, [1], 2);"#).is_err());
+    /// ```
+    pub(super) fn get(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        let (map, key, default) = match args {
+            [map, key] => (map, key, None),
+            [map, key, default] => (map, key, Some(default)),
+            _ => return Err(Error::ArgMismatch),
+        };
+        let map = self.visit(map)?;
+        let key = self.visit(key)?;
+        match (map.item(&key), default) {
+            (Err(Error::KeyNotFound), Some(default)) => self.visit(default),
+            (result, _) => result,
+        }
+    }
+
+    pub(super) fn set(&mut self, args: &[AstNodes]) -> Result<()> {
+        if args.len() != 3 {
             return Err(Error::ArgMismatch);
         }
         if let AstNodes::ReadVar(id) = args[0] {
-            let length = self.symbol_tables.symbol_crvalue_len(id)?;
-            let value = CrValue::Number(IBig::from(length));
-            Ok(value)
+            let key = self.visit(&args[1])?;
+            let value = self.visit(&args[2])?;
+            self.symbol_tables.symbol_modify_path(id, &[key], value)
+        } else {
+            Err(Error::NotAssignable)
+        }
+    }
+
+    /// Returns the keys of a map in ascending order.
+    pub(super) fn keys(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        if args.len() != 1 {
+            return Err(Error::ArgMismatch);
+        }
+        match self.visit(&args[0])? {
+            CrValue::Map(map) => Ok(CrValue::List(map.into_keys().map(CrValue::from).collect())),
+            _ => Err(Error::ArgMismatch),
+        }
+    }
+
+    /// Returns the values of a map, ordered by their keys.
+    pub(super) fn values(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        if args.len() != 1 {
+            return Err(Error::ArgMismatch);
+        }
+        match self.visit(&args[0])? {
+            CrValue::Map(map) => Ok(CrValue::List(map.into_values().collect())),
+            _ => Err(Error::ArgMismatch),
+        }
+    }
+
+    pub(super) fn type_of(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        if args.len() != 1 {
+            return Err(Error::ArgMismatch);
+        }
+        let value = self.visit(&args[0])?;
+        Ok(CrValue::Str(value.type_name().into()))
+    }
+
+    pub(super) fn assert(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        if args.len() != 1 {
+            return Err(Error::ArgMismatch);
+        }
+        if self.visit(&args[0])?.is_truthy()? {
+            Ok(CrValue::Void)
+        } else {
+            Err(Error::AssertionFailed("condition is false".into()))
+        }
+    }
+
+    pub(super) fn assert_eq(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        if args.len() != 2 {
+            return Err(Error::ArgMismatch);
+        }
+        let left = self.visit(&args[0])?;
+        let right = self.visit(&args[1])?;
+        if left == right {
+            Ok(CrValue::Void)
         } else {
-            Err(Error::ArgMismatch)
+            Err(Error::AssertionFailed(format!("{left} != {right}")))
         }
     }
 
+    pub(super) fn len(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        if args.len() != 1 {
+            return Err(Error::ArgMismatch);
+        }
+        let length = if let AstNodes::ReadVar(id) = args[0] {
+            self.symbol_tables.symbol_crvalue_len(id)?
+        } else {
+            self.visit(&args[0])?.length()?
+        };
+        Ok(CrValue::Number(IBig::from(length)))
+    }
+
     pub(super) fn remove(&mut self, args: &[AstNodes]) -> Result<CrValue> {
         if args.len() != 2 {
             return Err(Error::ArgMismatch);
         }
         if let AstNodes::ReadVar(id) = args[0] {
             let number = self.visit(&args[1])?;
-            let index = usize::try_from(number.as_int()?).unwrap();
+            let index = list_index(number.as_int()?)?;
 
             let list = self.symbol_tables.symbol_list_remove(id, index)?;
 
             Ok(list)
         } else {
-            Err(Error::ArgMismatch)
+            Err(Error::NotAssignable)
+        }
+    }
+
+    pub(super) fn pop(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        if args.len() != 1 {
+            return Err(Error::ArgMismatch);
+        }
+        if let AstNodes::ReadVar(id) = args[0] {
+            self.symbol_tables.symbol_list_pop(id)
+        } else {
+            Err(Error::NotAssignable)
+        }
+    }
+
+    /// Sorts a list of numbers in place, in ascending order or by a comparator which
+    /// returns a negative number, zero or a positive number like `cmp`. On an error the
+    /// list is left as it was. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::{Error, Interpreter};
+    /// use cara::frontend::{Lexer, Parser};
+    /// use cara::CaraError;
+    ///
+    /// let value = cara::eval("var a = [3, -1, 2, 0]; sort(a); a").unwrap();
+    /// assert_eq!(value, cara::eval("[-1, 0, 2, 3]").unwrap());
+    /// let value = cara::eval("var a = [1, 2, 3]; sort(a); a").unwrap();
+    /// assert_eq!(value, cara::eval("[1, 2, 3]").unwrap());
+    /// let value = cara::eval("var a = [1, 3, 2]; sort(a, fn(x, y) { return y - x; }); a").unwrap();
+    /// assert_eq!(value, cara::eval("[3, 2, 1]").unwrap());
+    ///
+    /// let error = cara::eval(r#"var a = [2, "x", 1]; sort(a);"#).unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::NonIntCalc)));
+    /// let error = cara::eval("var a = [2, 1]; sort(a, fn(x, y) { return x < y; });").unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::NonIntCalc)));
+    ///
+    /// let lexer = Lexer::new("var a = [2, 1]; sort(a, fn(x, y) { return x / 0; });".into());
+    /// let (ast, strings) = Parser::new(lexer).parse_compile_unit().unwrap();
+    /// let mut interpreter = Interpreter::new(strings);
+    /// assert!(matches!(interpreter.visit(&ast), Err(Error::DivByZero)));
+    /// assert_eq!(interpreter.get_variable("a"), cara::eval("[2, 1]").ok());
+    ///
+    /// // A comparator which isn't a consistent order still sorts without a panic.
+    /// let src = "
+    ///     var xs = range(0, 40);
+    ///     var n = 0;
+    ///     fn c(a, b) { n = n + 1; if n % 3 == 0 { return -1; } return 1; }
+    ///     sort(xs, c);
+    ///     [len(xs), sum(xs)]
+    /// ";
+    /// assert_eq!(cara::eval(src).unwrap(), cara::eval("[40, 780]").unwrap());
+    /// ```
+    pub(super) fn sort(&mut self, args: &[AstNodes]) -> Result<()> {
+        if args.is_empty() || args.len() > 2 {
+            return Err(Error::ArgMismatch);
+        }
+        let AstNodes::ReadVar(id) = args[0] else {
+            return Err(Error::NotAssignable);
+        };
+        let mut list = self
+            .symbol_tables
+            .symbol_clone_value(id)?
+            .as_list()?
+            .clone();
+
+        if let Some(comparator) = args.get(1) {
+            let comparator = self.visit(comparator)?;
+            if !comparator.is_callable() {
+                return Err(Error::ArgMismatch);
+            }
+            list = merge_sort(list, &mut |left, right| {
+                let args = vec![left.clone(), right.clone()];
+                match self.call_value(comparator.clone(), args)? {
+                    CrValue::Number(order) => Ok(order.cmp(&IBig::ZERO)),
+                    _ => Err(Error::NonIntCalc),
+                }
+            })?;
+        } else {
+            if !list.iter().all(|value| matches!(value, CrValue::Number(_))) {
+                return Err(Error::NonIntCalc);
+            }
+            list.sort_by(|left, right| left.partial_cmp(right).unwrap());
+        }
+
+        self.symbol_tables.symbol_list_replace(id, list)
+    }
+
+    /// Reverses a list variable in place. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::{CrValue, Error};
+    /// use cara::CaraError;
+    ///
+    /// let value = cara::eval("var a = [1, 2, 3]; var b = []; var c = [4]; reverse(a); reverse(b); reverse(c); [a, b, c]");
+    /// assert_eq!(value.unwrap(), cara::eval("[[3, 2, 1], [], [4]]").unwrap());
+    /// assert_eq!(cara::eval("var a = [1, 2]; reverse(a)").unwrap(), CrValue::Void);
+    ///
+    /// let error = cara::eval("reverse([1, 2]);").unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::NotAssignable)));
+    /// ```
+    pub(super) fn reverse(&mut self, args: &[AstNodes]) -> Result<()> {
+        if args.len() != 1 {
+            return Err(Error::ArgMismatch);
+        }
+        if let AstNodes::ReadVar(id) = args[0] {
+            self.symbol_tables.symbol_list_reverse(id)
+        } else {
+            Err(Error::NotAssignable)
+        }
+    }
+
+    /// Evaluates the only argument as a list of numbers.
+    fn number_list(&mut self, args: &[AstNodes]) -> Result<Vec<IBig>> {
+        if args.len() != 1 {
+            return Err(Error::ArgMismatch);
+        }
+        self.visit(&args[0])?
+            .as_list()?
+            .iter()
+            .map(|value| match value {
+                CrValue::Number(number) => Ok(number.clone()),
+                _ => Err(Error::NonIntCalc),
+            })
+            .collect()
+    }
+
+    /// `min`, `max` and `sum` fold a list of numbers. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::{CrValue, Error};
+    /// use cara::CaraError;
+    ///
+    /// let value = cara::eval("[min([3, -1, 2]), max([3, -1, 2]), sum([3, -1, 2]), sum([])]").unwrap();
+    /// assert_eq!(value, cara::eval("[-1, 3, 4, 0]").unwrap());
+    /// for src in ["min([])", "max([])"] {
+    ///     let error = cara::eval(src).unwrap_err();
+    ///     assert!(matches!(error, CaraError::Runtime(Error::EmptyList)));
+    /// }
+    /// ```
+    pub(super) fn min(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        let numbers = self.number_list(args)?;
+        let min = numbers.into_iter().min().ok_or(Error::EmptyList)?;
+        Ok(CrValue::Number(min))
+    }
+
+    pub(super) fn max(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        let numbers = self.number_list(args)?;
+        let max = numbers.into_iter().max().ok_or(Error::EmptyList)?;
+        Ok(CrValue::Number(max))
+    }
+
+    pub(super) fn sum(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        let numbers = self.number_list(args)?;
+        Ok(CrValue::Number(numbers.into_iter().sum()))
+    }
+
+    /// Returns the numbers from `start` up to but not including `end`, counting by
+    /// `step`, which may be negative. `range(n)` counts from 0 to `n`. The length of
+    /// the result is bounded like template lists. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::Error;
+    /// use cara::CaraError;
+    ///
+    /// let value = cara::eval("[range(3), range(1, 6, 2), range(3, 0, -1), range(3, 0)]").unwrap();
+    /// assert_eq!(value, cara::eval("[[0, 1, 2], [1, 3, 5], [3, 2, 1], []]").unwrap());
+    /// for src in ["range(10 ** 12)", "range(-(10 ** 30), 0)", "range(0, 10 ** 30, 10 ** 3)"] {
+    ///     let error = cara::eval(src).unwrap_err();
+    ///     assert!(matches!(error, CaraError::Runtime(Error::ListTooLong)), "{src}");
+    /// }
+    /// ```
+    pub(super) fn range(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        let (mut number, end, step) = match self.numbers(args)?.as_slice() {
+            [end] => (IBig::ZERO, end.clone(), IBig::ONE),
+            [start, end] => (start.clone(), end.clone(), IBig::ONE),
+            [start, end, step] => (start.clone(), end.clone(), step.clone()),
+            _ => return Err(Error::ArgMismatch),
+        };
+        if step == IBig::ZERO {
+            return Err(Error::ZeroStep);
+        }
+        let span = &end - &number;
+        let count = if (span > IBig::ZERO) == (step > IBig::ZERO) && span != IBig::ZERO {
+            // Rounds away from zero, since a partial step still adds a number.
+            (span + &step - step.signum()) / &step
+        } else {
+            IBig::ZERO
+        };
+        let count = usize::try_from(&count).map_err(|_| Error::ListTooLong)?;
+        if self.max_list_len.is_some_and(|max| count > max) {
+            return Err(Error::ListTooLong);
+        }
+        let mut values = Vec::with_capacity(count);
+        while (step > IBig::ZERO && number < end) || (step < IBig::ZERO && number > end) {
+            values.push(CrValue::Number(number.clone()));
+            number += &step;
+        }
+        Ok(CrValue::List(values))
+    }
+
+    /// Evaluates every argument as a number.
+    fn numbers(&mut self, args: &[AstNodes]) -> Result<Vec<IBig>> {
+        args.iter()
+            .map(|arg| self.visit(arg)?.as_int().cloned())
+            .collect()
+    }
+
+    /// Raises `base` to a whole exponent which isn't negative. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::Error;
+    /// use cara::CaraError;
+    ///
+    /// let value = cara::eval("[pow(2, 10), pow(-3, 3), pow(5, 0), pow(2, 100) == 1 << 100]").unwrap();
+    /// assert_eq!(value, cara::eval("[1024, -27, 1, true]").unwrap());
+    /// let error = cara::eval("pow(2, -1)").unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::NonIntCalc)));
+    /// ```
+    pub(super) fn pow(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        let numbers = self.numbers(args)?;
+        let [base, exp] = numbers.as_slice() else {
+            return Err(Error::ArgMismatch);
+        };
+        Ok(CrValue::Number(self.int_pow(base, exp)?))
+    }
+
+    /// Raises `base` to `exp` modulo `m`, giving a result between 0 and `m`. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::Error;
+    /// use cara::CaraError;
+    ///
+    /// let value = cara::eval("[modpow(4, 13, 497), modpow(-3, 3, 5), modpow(-2, 2, 5), modpow(7, 0, 3)]");
+    /// assert_eq!(value.unwrap(), cara::eval("[445, 3, 4, 1]").unwrap());
+    /// let error = cara::eval("modpow(2, 3, 0)").unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::DivByZero)));
+    /// let error = cara::eval("modpow(2, -1, 5)").unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::NonIntCalc)));
+    /// ```
+    pub(super) fn modpow(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        let numbers = self.numbers(args)?;
+        let [base, exp, modulus] = numbers.as_slice() else {
+            return Err(Error::ArgMismatch);
+        };
+        if *exp < IBig::ZERO {
+            return Err(Error::NonIntCalc);
+        }
+        if *modulus == IBig::ZERO {
+            return Err(Error::DivByZero);
+        }
+        let ring = ConstDivisor::new(modulus.unsigned_abs());
+        let result = ring.reduce(base.clone()).pow(&exp.unsigned_abs()).residue();
+        Ok(CrValue::Number(result.into()))
+    }
+
+    pub(super) fn abs(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        let numbers = self.numbers(args)?;
+        let [number] = numbers.as_slice() else {
+            return Err(Error::ArgMismatch);
+        };
+        Ok(CrValue::Number(number.clone().abs()))
+    }
+
+    /// Returns the greatest common divisor of two numbers, which is never negative.
+    /// `gcd(0, 0)` is 0. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::CrValue;
+    ///
+    /// let value = cara::eval("[gcd(12, 18), gcd(-12, 18), gcd(0, 5), gcd(0, 0), gcd(8, 15)]");
+    /// assert_eq!(value.unwrap(), cara::eval("[6, 6, 5, 0, 1]").unwrap());
+    /// let value = cara::eval("[lcm(4, 6), lcm(-4, 6), lcm(0, 5), lcm(0, 0), lcm(8, 15)]");
+    /// assert_eq!(value.unwrap(), cara::eval("[12, 12, 0, 0, 120]").unwrap());
+    /// assert_eq!(cara::eval("abs(-7)").unwrap(), CrValue::Number(7.into()));
+    /// ```
+    pub(super) fn gcd(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        let numbers = self.numbers(args)?;
+        let [a, b] = numbers.as_slice() else {
+            return Err(Error::ArgMismatch);
+        };
+        Ok(CrValue::Number(gcd(a, b)))
+    }
+
+    /// Returns the least common multiple of two numbers, which is never negative.
+    /// It's 0 if either number is.
+    pub(super) fn lcm(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        let numbers = self.numbers(args)?;
+        let [a, b] = numbers.as_slice() else {
+            return Err(Error::ArgMismatch);
+        };
+        if *a == IBig::ZERO || *b == IBig::ZERO {
+            return Ok(CrValue::Number(IBig::ZERO));
+        }
+        self.check_int_bits(a.bit_len() + b.bit_len())?;
+        Ok(CrValue::Number((a / gcd(a, b) * b).abs()))
+    }
+
+    /// Runs source code in the current scope and returns the value of its last statement.
+    pub(super) fn eval(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        if args.len() != 1 {
+            return Err(Error::ArgMismatch);
+        }
+        let CrValue::Str(src) = self.visit(&args[0])? else {
+            return Err(Error::ArgMismatch);
+        };
+        let ast = self.parse_source(&src).map_err(Error::Syntax)?;
+        self.visit(&ast)
+    }
+
+    /// Splits a list of pairs into the list of their first elements and the list of
+    /// their second ones. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::Error;
+    /// use cara::CaraError;
+    ///
+    /// let value = cara::eval(r#"unzip([[1, "a"], [2, "b"]])"#).unwrap();
+    /// assert_eq!(value, cara::eval(r#"[[1, 2], ["a", "b"]]"#).unwrap());
+    /// assert_eq!(cara::eval("unzip([])").unwrap(), cara::eval("[[], []]").unwrap());
+    ///
+    /// for src in ["unzip([[1, 2], [3]])", "unzip([[1, 2, 3]])", "unzip([1])"] {
+    ///     let error = cara::eval(src).unwrap_err();
+    ///     assert!(matches!(error, CaraError::Runtime(Error::ArgMismatch)));
+    /// }
+    /// ```
+    pub(super) fn unzip(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        if args.len() != 1 {
+            return Err(Error::ArgMismatch);
+        }
+        let pairs = self.visit(&args[0])?;
+        let (firsts, seconds) = pairs
+            .as_list()?
+            .iter()
+            .map(|pair| match pair.as_list().map(Vec::as_slice) {
+                Ok([first, second]) => Ok((first.clone(), second.clone())),
+                _ => Err(Error::ArgMismatch),
+            })
+            .collect::<Result<(Vec<CrValue>, Vec<CrValue>)>>()?;
+        Ok(CrValue::List(vec![
+            CrValue::List(firsts),
+            CrValue::List(seconds),
+        ]))
+    }
+
+    /// Evaluates a list and a function to call on its elements.
+    fn list_and_function(
+        &mut self,
+        list: &AstNodes,
+        function: &AstNodes,
+    ) -> Result<(Vec<CrValue>, CrValue)> {
+        let list = match self.visit(list)? {
+            CrValue::List(list) => list,
+            CrValue::Frozen(list) => list.as_ref().clone(),
+            _ => return Err(Error::ArgMismatch),
+        };
+        let function = self.visit(function)?;
+        if !function.is_callable() {
+            return Err(Error::ArgMismatch);
+        }
+        Ok((list, function))
+    }
+
+    pub(super) fn map(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        let [list, function] = args else {
+            return Err(Error::ArgMismatch);
+        };
+        let (list, function) = self.list_and_function(list, function)?;
+        let values = list
+            .into_iter()
+            .map(|value| self.call_value(function.clone(), vec![value]))
+            .collect::<Result<Vec<CrValue>>>()?;
+        Ok(CrValue::List(values))
+    }
+
+    /// Keeps the elements for which the predicate holds. The predicate must give a
+    /// bool or a number, like any condition. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::Error;
+    /// use cara::CaraError;
+    ///
+    /// let value = cara::eval("filter([1, 2, 3, 4], fn(x) { return x % 2 == 0; })").unwrap();
+    /// assert_eq!(value, cara::eval("[2, 4]").unwrap());
+    /// let value = cara::eval("filter([0, 1, 2], fn(x) { return x; })").unwrap();
+    /// assert_eq!(value, cara::eval("[1, 2]").unwrap());
+    /// let value = cara::eval("filter([], fn(x) { return true; })").unwrap();
+    /// assert_eq!(value, cara::eval("[]").unwrap());
+    ///
+    /// let error = cara::eval(r#"filter([1], fn(x) { return "yes"; })"#).unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::NonBoolCondition)));
+    /// let error = cara::eval("filter(1, fn(x) { return true; })").unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::ArgMismatch)));
+    /// let error = cara::eval("filter([1], 2)").unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::ArgMismatch)));
+    /// ```
+    pub(super) fn filter(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        let [list, predicate] = args else {
+            return Err(Error::ArgMismatch);
+        };
+        let (list, predicate) = self.list_and_function(list, predicate)?;
+        let mut values = Vec::new();
+        for value in list {
+            if self
+                .call_value(predicate.clone(), vec![value.clone()])?
+                .is_truthy()?
+            {
+                values.push(value);
+            }
+        }
+        Ok(CrValue::List(values))
+    }
+
+    /// Folds the list from the left, starting from `init`. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::{CrValue, Error};
+    /// use cara::CaraError;
+    ///
+    /// let value = cara::eval("reduce([1, 2, 3], fn(acc, x) { return acc + x; }, 0)").unwrap();
+    /// assert_eq!(value, CrValue::Number(6.into()));
+    /// let value = cara::eval("reduce([1, 2, 3], fn(acc, x) { return [acc, x]; }, 0)").unwrap();
+    /// assert_eq!(value, cara::eval("[[[0, 1], 2], 3]").unwrap());
+    /// let value = cara::eval("reduce([], fn(acc, x) { return acc + x; }, 5)").unwrap();
+    /// assert_eq!(value, CrValue::Number(5.into()));
+    ///
+    /// let error = cara::eval("reduce([1], 2, 0)").unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::ArgMismatch)));
+    /// ```
+    pub(super) fn reduce(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        let [list, function, init] = args else {
+            return Err(Error::ArgMismatch);
+        };
+        let (list, function) = self.list_and_function(list, function)?;
+        let init = self.visit(init)?;
+        list.into_iter().try_fold(init, |acc, value| {
+            self.call_value(function.clone(), vec![acc, value])
+        })
+    }
+
+    /// Calls the function on the elements of both lists at each index, as far as
+    /// the shorter list goes. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::Error;
+    /// use cara::CaraError;
+    ///
+    /// let add = "fn(a, b) { return a + b; }";
+    /// let value = cara::eval(&format!("zip_with([1, 2, 3], [10, 20, 30], {add})")).unwrap();
+    /// assert_eq!(value, cara::eval("[11, 22, 33]").unwrap());
+    /// let value = cara::eval(&format!("[zip_with([1, 2, 3], [10], {add}), zip_with([1], [10, 20], {add})]"));
+    /// assert_eq!(value.unwrap(), cara::eval("[[11], [11]]").unwrap());
+    /// let value = cara::eval(&format!("zip_with([], [1, 2], {add})")).unwrap();
+    /// assert_eq!(value, cara::eval("[]").unwrap());
+    ///
+    /// let error = cara::eval("zip_with([1], [2], 3)").unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::ArgMismatch)));
+    /// ```
+    pub(super) fn zip_with(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        if args.len() != 3 {
+            return Err(Error::ArgMismatch);
+        }
+        let left = self.visit(&args[0])?;
+        let right = self.visit(&args[1])?;
+        let function = self.visit(&args[2])?;
+        if !function.is_callable() {
+            return Err(Error::ArgMismatch);
+        }
+        let values = zip(left.as_list()?, right.as_list()?)
+            .map(|(left, right)| {
+                self.call_value(function.clone(), vec![left.clone(), right.clone()])
+            })
+            .collect::<Result<Vec<CrValue>>>()?;
+        Ok(CrValue::List(values))
+    }
+
+    /// Returns a copy of the list without consecutive repeated elements. \
+    /// Example
+    /// ```rust
+    /// let value = cara::eval("var a = [1, 1, 2, 2, 2, 1, 3, 3]; [dedup(a), a]").unwrap();
+    /// assert_eq!(value, cara::eval("[[1, 2, 1, 3], [1, 1, 2, 2, 2, 1, 3, 3]]").unwrap());
+    /// let value = cara::eval(r#"[dedup([]), dedup(["a", "a"]), dedup([[1], [1], [2]]), dedup([1, 1.0])]"#);
+    /// assert_eq!(value.unwrap(), cara::eval(r#"[[], ["a"], [[1], [2]], [1]]"#).unwrap());
+    /// ```
+    pub(super) fn dedup(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        if args.len() != 1 {
+            return Err(Error::ArgMismatch);
+        }
+        let mut list = self.visit(&args[0])?.as_list()?.clone();
+        list.dedup();
+        Ok(CrValue::List(list))
+    }
+
+    /// Returns a copy of the list rotated left by `n` places, or right if `n` is negative. \
+    /// Example
+    /// ```rust
+    /// let value = cara::eval("[rotate([1, 2, 3, 4], 1), rotate([1, 2, 3, 4], -1), rotate([1, 2, 3, 4], 0)]");
+    /// assert_eq!(value.unwrap(), cara::eval("[[2, 3, 4, 1], [4, 1, 2, 3], [1, 2, 3, 4]]").unwrap());
+    /// let value = cara::eval("[rotate([1, 2, 3], 7), rotate([1, 2, 3], -7), rotate([1, 2, 3], 10 ** 30)]");
+    /// assert_eq!(value.unwrap(), cara::eval("[[2, 3, 1], [3, 1, 2], [2, 3, 1]]").unwrap());
+    /// assert_eq!(cara::eval("rotate([], 3)").unwrap(), cara::eval("[]").unwrap());
+    /// ```
+    pub(super) fn rotate(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        if args.len() != 2 {
+            return Err(Error::ArgMismatch);
+        }
+        let mut list = self.visit(&args[0])?.as_list()?.clone();
+        let number = self.visit(&args[1])?;
+        if !list.is_empty() {
+            let len = IBig::from(list.len());
+            let n = (number.as_int()? % &len + &len) % len;
+            list.rotate_left(list_index(&n)?);
+        }
+        Ok(CrValue::List(list))
+    }
+
+    /// Calls a function without arguments `n` times and returns the results. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::{CrValue, Error, Interpreter};
+    /// use cara::frontend::{Lexer, Parser};
+    /// use cara::CaraError;
+    ///
+    /// let src = "var n = 0; fn next() { n = n + 1; return n; } repeat_with(next, 3)";
+    /// assert_eq!(cara::eval(src).unwrap(), cara::eval("[1, 2, 3]").unwrap());
+    /// let src = "repeat_with(fn() { return 7; }, 2)";
+    /// assert_eq!(cara::eval(src).unwrap(), cara::eval("[7, 7]").unwrap());
+    /// let src = "repeat_with(fn() { return 1 / 0; }, 0)";
+    /// assert_eq!(cara::eval(src).unwrap(), cara::eval("[]").unwrap());
+    /// let error = cara::eval("repeat_with(fn() { return 7; }, -1)").unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::InvalidArrayLen)));
+    /// let error = cara::eval("repeat_with(fn() { return 7; }, 10 ** 12)").unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::ListTooLong)));
+    ///
+    /// // Native functions are called by name as well.
+    /// let lexer = Lexer::new("repeat_with(answer, 2)".into());
+    /// let (ast, strings) = Parser::new(lexer).parse_compile_unit().unwrap();
+    /// let mut interpreter = Interpreter::new(strings);
+    /// interpreter.register_fn("answer", |_| Ok(CrValue::Number(42.into())));
+    /// assert_eq!(interpreter.visit(&ast).unwrap(), cara::eval("[42, 42]").unwrap());
+    /// ```
+    pub(super) fn repeat_with(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        let [function, number] = args else {
+            return Err(Error::ArgMismatch);
+        };
+        // Named functions are called by name so that native functions work too.
+        let values = if let AstNodes::ReadVar(id) = function {
+            let times = self.repeat_count(number)?;
+            (0..times)
+                .map(|_| self.call_function(*id, Vec::new()))
+                .collect::<Result<Vec<CrValue>>>()?
+        } else {
+            let function = self.visit(function)?;
+            let times = self.repeat_count(number)?;
+            (0..times)
+                .map(|_| self.call_value(function.clone(), Vec::new()))
+                .collect::<Result<Vec<CrValue>>>()?
+        };
+        Ok(CrValue::List(values))
+    }
+
+    /// Evaluates how many times to repeat something, which can't be negative and is
+    /// bounded like the length of template lists.
+    fn repeat_count(&mut self, number: &AstNodes) -> Result<usize> {
+        let number = self.visit(number)?;
+        let number = number.as_int()?;
+        if *number < IBig::ZERO {
+            return Err(Error::InvalidArrayLen);
         }
+        let count = usize::try_from(number).map_err(|_| Error::ListTooLong)?;
+        if self.max_list_len.is_some_and(|max| count > max) {
+            return Err(Error::ListTooLong);
+        }
+        Ok(count)
+    }
+
+    pub(super) fn memoize(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        if args.len() != 1 {
+            return Err(Error::ArgMismatch);
+        }
+        let function = self.visit(&args[0])?;
+        if !function.is_callable() {
+            return Err(Error::ArgMismatch);
+        }
+        Ok(CrValue::Memoized(
+            function.into(),
+            Rc::new(RefCell::new(BTreeMap::new())),
+        ))
+    }
+
+    pub(super) fn freeze(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        if args.len() != 1 {
+            return Err(Error::ArgMismatch);
+        }
+        match self.visit(&args[0])? {
+            CrValue::List(list) => Ok(CrValue::Frozen(list.into())),
+            frozen @ CrValue::Frozen(_) => Ok(frozen),
+            _ => Err(Error::ArgMismatch),
+        }
+    }
+
+    pub(super) fn thaw(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        if args.len() != 1 {
+            return Err(Error::ArgMismatch);
+        }
+        let list = self.visit(&args[0])?;
+        Ok(CrValue::List(list.as_list()?.clone()))
+    }
+
+    pub(super) fn ord(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        if args.len() != 1 {
+            return Err(Error::ArgMismatch);
+        }
+        match self.visit(&args[0])? {
+            CrValue::Char(ch) => Ok(CrValue::Number(IBig::from(u32::from(ch)))),
+            _ => Err(Error::ArgMismatch),
+        }
+    }
+
+    /// Formats any value as a string, the way `print` shows it. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::CrValue;
+    ///
+    /// assert_eq!(cara::eval("str(42)").unwrap(), CrValue::Str("42".into()));
+    /// assert_eq!(cara::eval(r#"str("a") + str([1])"#).unwrap(), CrValue::Str("a[1,]".into()));
+    /// ```
+    pub(super) fn str(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        if args.len() != 1 {
+            return Err(Error::ArgMismatch);
+        }
+        let value = self.visit(&args[0])?;
+        Ok(CrValue::Str(value.to_string()))
+    }
+
+    /// Parses a decimal integer, ignoring surrounding whitespace so lines of input
+    /// can be passed straight in. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::CrValue;
+    ///
+    /// assert_eq!(cara::eval(r#"int("100") + 1"#).unwrap(), CrValue::Number(101.into()));
+    /// assert_eq!(cara::eval(r#"int(" -7\n")"#).unwrap(), CrValue::Number((-7).into()));
+    /// ```
+    pub(super) fn int(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        if args.len() != 1 {
+            return Err(Error::ArgMismatch);
+        }
+        match self.visit(&args[0])? {
+            CrValue::Number(number) => Ok(CrValue::Number(number)),
+            CrValue::Str(string) => string
+                .trim()
+                .parse()
+                .map(CrValue::Number)
+                .map_err(|_| Error::ParseInt(string.clone())),
+            _ => Err(Error::ArgMismatch),
+        }
+    }
+
+    pub(super) fn chr(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        if args.len() != 1 {
+            return Err(Error::ArgMismatch);
+        }
+        let number = self.visit(&args[0])?;
+        u32::try_from(number.as_int()?)
+            .ok()
+            .and_then(char::from_u32)
+            .map(CrValue::Char)
+            .ok_or(Error::ArgMismatch)
+    }
+
+    /// Splits a string into its characters. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::Error;
+    /// use cara::CaraError;
+    ///
+    /// let value = cara::eval(r#"chars("héllo")"#).unwrap();
+    /// assert_eq!(value, cara::eval("['h', 'é', 'l', 'l', 'o']").unwrap());
+    /// let value = cara::eval(r#"[len(chars("日本語")), len("日本語"), chars("")]"#).unwrap();
+    /// assert_eq!(value, cara::eval("[3, 3, []]").unwrap());
+    ///
+    /// let error = cara::eval("chars(['a'])").unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::ArgMismatch)));
+    /// ```
+    pub(super) fn chars(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        if args.len() != 1 {
+            return Err(Error::ArgMismatch);
+        }
+        match self.visit(&args[0])? {
+            CrValue::Str(string) => Ok(CrValue::List(string.chars().map(CrValue::Char).collect())),
+            _ => Err(Error::ArgMismatch),
+        }
+    }
+
+    /// Returns -1, 0 or 1 as the first argument is less than, equal to or greater
+    /// than the second.
+    pub(super) fn cmp(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        if args.len() != 2 {
+            return Err(Error::ArgMismatch);
+        }
+        let left = self.visit(&args[0])?;
+        let right = self.visit(&args[1])?;
+        let ordering = match left.partial_cmp(&right).ok_or(Error::NonIntCalc)? {
+            Ordering::Less => -1,
+            Ordering::Equal => 0,
+            Ordering::Greater => 1,
+        };
+        Ok(CrValue::Number(IBig::from(ordering)))
+    }
+
+    /// Splits a string on `\n` or `\r\n`, without an empty line after a trailing newline. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::Error;
+    /// use cara::CaraError;
+    ///
+    /// let expected = cara::eval(r#"["a", "b", "c"]"#).unwrap();
+    /// assert_eq!(cara::eval(r#"lines("a\nb\nc")"#).unwrap(), expected);
+    /// assert_eq!(cara::eval(r#"lines("a\r\nb\r\nc")"#).unwrap(), expected);
+    /// assert_eq!(cara::eval(r#"lines("a\nb\r\nc\n")"#).unwrap(), expected);
+    /// let value = cara::eval(r#"[lines(""), lines("\n"), lines("a\n\nb")]"#).unwrap();
+    /// assert_eq!(value, cara::eval(r#"[[], [""], ["a", "", "b"]]"#).unwrap());
+    ///
+    /// let error = cara::eval("lines(1)").unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::ArgMismatch)));
+    /// ```
+    pub(super) fn lines(&mut self, args: &[AstNodes]) -> Result<CrValue> {
+        if args.len() != 1 {
+            return Err(Error::ArgMismatch);
+        }
+        match self.visit(&args[0])? {
+            CrValue::Str(string) => Ok(CrValue::List(
+                string
+                    .lines()
+                    .map(|line| CrValue::Str(line.into()))
+                    .collect(),
+            )),
+            _ => Err(Error::ArgMismatch),
+        }
+    }
+}
+
+/// Sorts `list` stably by `compare`, stopping at its first error. Unlike
+/// `slice::sort_by` it doesn't need `compare` to be a total order, which a script's
+/// comparator may not be.
+fn merge_sort<F>(mut list: Vec<CrValue>, compare: &mut F) -> Result<Vec<CrValue>>
+where
+    F: FnMut(&CrValue, &CrValue) -> Result<Ordering>,
+{
+    if list.len() < 2 {
+        return Ok(list);
+    }
+    let right = list.split_off(list.len() / 2);
+    let left = merge_sort(list, compare)?;
+    let right = merge_sort(right, compare)?;
+
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+    while let (Some(l), Some(r)) = (left.peek(), right.peek()) {
+        let next = if compare(l, r)? == Ordering::Greater {
+            right.next()
+        } else {
+            left.next()
+        };
+        merged.extend(next);
+    }
+    merged.extend(left);
+    merged.extend(right);
+    Ok(merged)
+}
+
+fn gcd(a: &IBig, b: &IBig) -> IBig {
+    if *a == IBig::ZERO {
+        return b.clone().abs();
+    }
+    if *b == IBig::ZERO {
+        return a.clone().abs();
     }
+    a.gcd(b).into()
 }
@@ -1,24 +1,94 @@
+use alloc::collections::{BTreeMap, BTreeSet};
 use alloc::{borrow::ToOwned, vec};
-use alloc::{rc::Rc, string::String, vec::Vec};
+use alloc::{
+    boxed::Box,
+    rc::Rc,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt;
 use core::iter::zip;
-use dashu_int::IBig;
-use value::CrValue;
+use core::mem;
+use dashu_int::{ops::BitTest, IBig};
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant};
 
 use crate::ast::{AstNodes, Op};
-use result::{Error, Result};
-use scope::{Symbol, SymbolTable, SymbolTables};
+use crate::frontend::{Lexer, ParseError, Parser, Span};
+use scope::{Symbol, SymbolTables};
+use value::{list_index, MemoKey};
 
 mod builtins;
+mod passes;
+mod prelude;
 mod result;
 mod scope;
 mod value;
 
 pub use builtins::set_printer;
+pub use passes::fold_template_lists;
+pub use result::{Error, Result};
+pub use value::{CrValue, DisplayValue, MapKey};
+
+/// A function implemented by the host, called with the evaluated arguments.
+pub type NativeFunction = Box<dyn Fn(&[CrValue]) -> Result<CrValue>>;
+
+/// Receives the text a script prints.
+pub type Printer = Box<dyn FnMut(fmt::Arguments)>;
+
+/// Supplies the lines a script reads with `input`, or `None` at the end of input.
+pub type Reader = Box<dyn FnMut() -> Option<String>>;
+
+/// A transformation applied to the AST before it's interpreted.
+pub type Pass = Box<dyn Fn(AstNodes) -> AstNodes>;
+
+/// The largest amount a number may be shifted left by, which keeps scripts from
+/// allocating astronomically large integers.
+const MAX_LEFT_SHIFT: usize = 1 << 24;
+
+/// The default bound on the bit length of arithmetic results, a little over 16 million bits.
+const DEFAULT_MAX_INT_BITS: usize = 1 << 24;
+
+/// The default bound on the length of template lists, a little over 16 million elements.
+const DEFAULT_MAX_LIST_LEN: usize = 1 << 24;
+
+/// How many nodes are visited between two looks at the clock when running with a deadline.
+#[cfg(feature = "std")]
+const DEADLINE_CHECK_INTERVAL: u32 = 1024;
 
 /// The interpreter
 pub struct Interpreter {
     symbol_tables: SymbolTables,
-    string_table: Vec<String>,
+    /// Body of the function currently being executed, used to spot self tail calls.
+    running: Option<Rc<Vec<AstNodes>>>,
+    /// Set by `return` when its value is a call, so that call knows it is in tail position.
+    tail_call: bool,
+    natives: BTreeMap<String, NativeFunction>,
+    passes: Vec<Pass>,
+    /// Where `print` writes to instead of the global printer, if set.
+    printer: Option<Printer>,
+    /// Where `input` reads from, if set.
+    reader: Option<Reader>,
+    /// How many digits of a number `print` shows at most, if limited.
+    max_display_digits: Option<usize>,
+    /// Where the last error was raised, if an operator or an indexing raised it.
+    error_span: Option<Span>,
+    /// How many times each kind of node was visited, if counting is enabled.
+    stats: Option<BTreeMap<&'static str, u64>>,
+    /// Builtins the host turned off, which scripts can't call.
+    disabled_builtins: BTreeSet<String>,
+    /// How many more nodes may be visited, if the script runs under a step limit.
+    steps_left: Option<u64>,
+    /// The largest bit length an arithmetic result may have, if bounded.
+    max_int_bits: Option<usize>,
+    /// The longest list a template list may create, if bounded.
+    max_list_len: Option<usize>,
+    /// When the script has to stop, if it runs under [`Interpreter::visit_with_timeout`].
+    #[cfg(feature = "std")]
+    deadline: Option<Instant>,
+    /// Nodes visited since the deadline was last checked.
+    #[cfg(feature = "std")]
+    ticks: u32,
 }
 
 impl Interpreter {
@@ -26,18 +96,398 @@ impl Interpreter {
     /// Example
     /// ```rust
     /// use cara::backend::Interpreter;
-    /// let interpreter = Interpreter::new();
+    /// let interpreter = Interpreter::new(Vec::new());
     /// ```
     #[must_use]
     pub fn new(string_table: Vec<String>) -> Self {
         Self {
-            symbol_tables: vec![SymbolTable::new()].into(),
-            string_table,
+            symbol_tables: SymbolTables::new(string_table),
+            running: None,
+            tail_call: false,
+            natives: BTreeMap::new(),
+            passes: Vec::new(),
+            printer: None,
+            reader: None,
+            max_display_digits: None,
+            error_span: None,
+            stats: None,
+            disabled_builtins: BTreeSet::new(),
+            steps_left: None,
+            max_int_bits: Some(DEFAULT_MAX_INT_BITS),
+            max_list_len: Some(DEFAULT_MAX_LIST_LEN),
+            #[cfg(feature = "std")]
+            deadline: None,
+            #[cfg(feature = "std")]
+            ticks: 0,
         }
     }
 
+    /// Defines the functions of the prelude, which is written in Cara itself, as globals. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::{CrValue, Interpreter};
+    /// use cara::frontend::{Lexer, Parser};
+    ///
+    /// let lexer = Lexer::new("product([1, 2, 3, 4])".into());
+    /// let (ast, strings) = Parser::new(lexer).parse_compile_unit().unwrap();
+    ///
+    /// let mut interpreter = Interpreter::new(strings).with_prelude();
+    /// assert_eq!(interpreter.visit(&ast).unwrap(), CrValue::Number(24.into()));
+    /// ```
+    #[must_use]
+    pub fn with_prelude(mut self) -> Self {
+        let ast = self
+            .parse_source(prelude::PRELUDE)
+            .expect("the prelude should parse");
+        self.visit(&ast).expect("the prelude should run");
+        self
+    }
+
+    /// Parses more source for this interpreter, giving its names the ids they already
+    /// have here, so the AST can be run after the ones before it.
+    pub fn parse_source(&mut self, src: &str) -> core::result::Result<AstNodes, ParseError> {
+        let (ast, strings) = Parser::new(Lexer::new(src.into())).parse_compile_unit()?;
+        Ok(ast.map_ids(&mut |id| self.symbol_tables.intern(&strings[id])))
+    }
+
     pub fn string_table(&self) -> &[String] {
-        &self.string_table
+        self.symbol_tables.string_table()
+    }
+
+    /// Defines a global variable, or replaces it if it already exists. The name doesn't
+    /// need to appear in the script, so bindings can be made from names computed at
+    /// runtime. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::{CrValue, Interpreter};
+    /// use cara::frontend::{Lexer, Parser};
+    ///
+    /// let lexer = Lexer::new("x = x * 2;".into());
+    /// let (ast, strings) = Parser::new(lexer).parse_compile_unit().unwrap();
+    ///
+    /// let mut interpreter = Interpreter::new(strings);
+    /// interpreter.set_variable("x", CrValue::Number(10.into()));
+    /// interpreter.visit(&ast).unwrap();
+    /// assert_eq!(interpreter.get_variable("x"), Some(CrValue::Number(20.into())));
+    ///
+    /// for i in 0..3 {
+    ///     interpreter.set_variable(&format!("var_{i}"), CrValue::Number(i.into()));
+    /// }
+    /// assert_eq!(interpreter.get_variable("var_2"), Some(CrValue::Number(2.into())));
+    /// ```
+    pub fn set_variable(&mut self, name: &str, value: CrValue) {
+        let id = self.symbol_tables.intern(name);
+        self.symbol_tables.insert_global(Symbol::Var(id, value));
+    }
+
+    /// Defines a global constant, which scripts can read but neither assign to nor
+    /// change in place. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::{CrValue, Error, Interpreter};
+    /// use cara::frontend::{Lexer, Parser};
+    ///
+    /// let lexer = Lexer::new("var doubled = limit * 2; limit = 1;".into());
+    /// let (ast, strings) = Parser::new(lexer).parse_compile_unit().unwrap();
+    ///
+    /// let mut interpreter = Interpreter::new(strings);
+    /// interpreter.define_constant("limit", CrValue::Number(50.into()));
+    /// assert!(matches!(interpreter.visit(&ast), Err(Error::BadAssign)));
+    /// assert_eq!(interpreter.get_variable("doubled"), Some(CrValue::Number(100.into())));
+    /// assert_eq!(interpreter.get_variable("limit"), Some(CrValue::Number(50.into())));
+    ///
+    /// for src in ["c[0] = 5;", "append(c, 4);", "sort(c);", r#"set(m, "a", 2);"#] {
+    ///     let lexer = Lexer::new(src.into());
+    ///     let (ast, strings) = Parser::new(lexer).parse_compile_unit().unwrap();
+    ///     let mut interpreter = Interpreter::new(strings);
+    ///     interpreter.define_constant("c", cara::eval("[3, 2, 1]").unwrap());
+    ///     interpreter.define_constant("m", cara::eval(r#"{"a": 1}"#).unwrap());
+    ///     assert!(matches!(interpreter.visit(&ast), Err(Error::BadAssign)), "{src}");
+    ///     assert_eq!(interpreter.get_variable("c"), cara::eval("[3, 2, 1]").ok());
+    ///     assert_eq!(interpreter.get_variable("m"), cara::eval(r#"{"a": 1}"#).ok());
+    /// }
+    ///
+    /// // The same goes for constants defined by scripts, but parameters are variables.
+    /// let error = cara::eval("const c = [1]; push(c, 2);").unwrap_err();
+    /// assert!(matches!(error, cara::CaraError::Runtime(Error::BadAssign)));
+    /// let src = "fn wrap(list) { push(list, 1); list = [list]; return list; } wrap([0])";
+    /// assert_eq!(cara::eval(src).unwrap(), cara::eval("[[0, 1]]").unwrap());
+    /// ```
+    pub fn define_constant(&mut self, name: &str, value: CrValue) {
+        let id = self.symbol_tables.intern(name);
+        self.symbol_tables.insert_global(Symbol::Const(id, value));
+    }
+
+    /// Returns the value of the variable `name` visible from the current scope.
+    #[must_use]
+    pub fn get_variable(&self, name: &str) -> Option<CrValue> {
+        let id = self.symbol_tables.lookup(name)?;
+        self.symbol_tables.symbol_clone_value(id).ok()
+    }
+
+    /// Limits how many AST nodes may still be visited, so runaway scripts stop with
+    /// [`Error::StepLimitExceeded`]. `None` removes the limit. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::{Error, Interpreter};
+    /// use cara::frontend::{Lexer, Parser};
+    ///
+    /// let lexer = Lexer::new("while true { }".into());
+    /// let (ast, strings) = Parser::new(lexer).parse_compile_unit().unwrap();
+    ///
+    /// let mut interpreter = Interpreter::new(strings);
+    /// interpreter.set_step_limit(Some(1000));
+    /// assert!(matches!(interpreter.visit(&ast), Err(Error::StepLimitExceeded)));
+    /// ```
+    pub fn set_step_limit(&mut self, limit: Option<u64>) {
+        self.steps_left = limit;
+    }
+
+    /// Bounds the bit length of the results of shifts and multiplications, so scripts
+    /// can't exhaust memory with huge integers. Exceeding it gives [`Error::IntTooLarge`].
+    /// `None` removes the bound; by default it is 2^24 bits. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::{Error, Interpreter};
+    /// use cara::frontend::{Lexer, Parser};
+    ///
+    /// let lexer = Lexer::new("1 << 100".into());
+    /// let (ast, strings) = Parser::new(lexer).parse_compile_unit().unwrap();
+    ///
+    /// let mut interpreter = Interpreter::new(strings);
+    /// interpreter.set_max_int_bits(Some(64));
+    /// assert!(matches!(interpreter.visit(&ast), Err(Error::IntTooLarge)));
+    /// ```
+    pub fn set_max_int_bits(&mut self, limit: Option<usize>) {
+        self.max_int_bits = limit;
+    }
+
+    /// Bounds the length of lists created by template lists like `[0; n]` and of
+    /// strings repeated with `*`, or removes the bound with `None`. The default is about 16 million elements. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::{Error, Interpreter};
+    /// use cara::frontend::{Lexer, Parser};
+    ///
+    /// let lexer = Lexer::new("var a = [0; 10]; var b = [0; 11];".into());
+    /// let (ast, strings) = Parser::new(lexer).parse_compile_unit().unwrap();
+    ///
+    /// let mut interpreter = Interpreter::new(strings);
+    /// interpreter.set_max_list_len(Some(10));
+    /// assert!(matches!(interpreter.visit(&ast), Err(Error::ListTooLong)));
+    /// ```
+    pub fn set_max_list_len(&mut self, limit: Option<usize>) {
+        self.max_list_len = limit;
+    }
+
+    /// Makes this interpreter print through `printer` rather than the printer set
+    /// by [`set_printer`], so a closure can collect its output. \
+    /// Example
+    /// ```rust
+    /// use std::cell::RefCell;
+    /// use std::fmt::Write;
+    /// use std::rc::Rc;
+    ///
+    /// use cara::backend::Interpreter;
+    /// use cara::frontend::{Lexer, Parser};
+    ///
+    /// let lexer = Lexer::new("println(1, 2);".into());
+    /// let (ast, strings) = Parser::new(lexer).parse_compile_unit().unwrap();
+    ///
+    /// let output = Rc::new(RefCell::new(String::new()));
+    /// let buffer = output.clone();
+    /// let mut interpreter = Interpreter::new(strings);
+    /// interpreter.set_printer(Box::new(move |args| buffer.borrow_mut().write_fmt(args).unwrap()));
+    /// interpreter.visit(&ast).unwrap();
+    /// assert_eq!(*output.borrow(), "1 2\n");
+    /// ```
+    pub fn set_printer(&mut self, printer: Printer) {
+        self.printer = Some(printer);
+    }
+
+    /// Makes `input()` read lines through `reader`. Without a reader, `input` fails
+    /// with [`Error::NoReader`]. At the end of input it returns void. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::{CrValue, Interpreter};
+    /// use cara::frontend::{Lexer, Parser};
+    ///
+    /// let src = r#"var a = int(input()); var b = int(input()); [a + b, typeof(input())]"#;
+    /// let (ast, strings) = Parser::new(Lexer::new(src.into())).parse_compile_unit().unwrap();
+    ///
+    /// let mut lines = vec!["40", "2"].into_iter();
+    /// let mut interpreter = Interpreter::new(strings);
+    /// interpreter.set_reader(Box::new(move || lines.next().map(String::from)));
+    /// let expected = cara::eval(r#"[42, "void"]"#).unwrap();
+    /// assert_eq!(interpreter.visit(&ast).unwrap(), expected);
+    /// ```
+    pub fn set_reader(&mut self, reader: Reader) {
+        self.reader = Some(reader);
+    }
+
+    /// Makes `print` shorten numbers with more than `digits` digits to their first and
+    /// last few digits and their length, like `1234...5678 (100000 digits)`. `None`
+    /// prints numbers in full, which is the default. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::Interpreter;
+    /// use cara::frontend::{Lexer, Parser};
+    /// use std::{cell::RefCell, fmt::Write, rc::Rc};
+    ///
+    /// let lexer = Lexer::new("println(1 << 100, 42);".into());
+    /// let (ast, strings) = Parser::new(lexer).parse_compile_unit().unwrap();
+    ///
+    /// let output = Rc::new(RefCell::new(String::new()));
+    /// let buffer = output.clone();
+    /// let mut interpreter = Interpreter::new(strings);
+    /// interpreter.set_printer(Box::new(move |args| buffer.borrow_mut().write_fmt(args).unwrap()));
+    /// interpreter.set_max_display_digits(Some(6));
+    /// interpreter.visit(&ast).unwrap();
+    /// assert_eq!(*output.borrow(), "126...376 (31 digits) 42\n");
+    /// ```
+    pub fn set_max_display_digits(&mut self, digits: Option<usize>) {
+        self.max_display_digits = digits;
+    }
+
+    /// Starts or stops counting how many times each kind of AST node is visited.
+    /// Starting again clears the counts.
+    pub fn set_stats_enabled(&mut self, enabled: bool) {
+        self.stats = enabled.then(BTreeMap::new);
+    }
+
+    /// Returns where the error the last script stopped with was raised, if an operator
+    /// or an indexing raised it. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::{Error, Interpreter};
+    /// use cara::frontend::{Lexer, Parser, Span};
+    ///
+    /// let src = "var xs = [1, 2, 3];\nvar i = 0;\nvar y = xs[1] / i;";
+    /// let (ast, strings) = Parser::new(Lexer::new(src.into())).parse_compile_unit().unwrap();
+    ///
+    /// let mut interpreter = Interpreter::new(strings);
+    /// assert!(matches!(interpreter.visit(&ast), Err(Error::DivByZero)));
+    /// assert_eq!(interpreter.error_span(), Some(Span { line: 3, col: 15 }));
+    ///
+    /// let src = "var xs = [1, 2, 3];\n\n  xs[3]";
+    /// let (ast, strings) = Parser::new(Lexer::new(src.into())).parse_compile_unit().unwrap();
+    ///
+    /// let mut interpreter = Interpreter::new(strings);
+    /// assert!(matches!(interpreter.visit(&ast), Err(Error::IndexOutOfRange)));
+    /// assert_eq!(interpreter.error_span(), Some(Span { line: 3, col: 5 }));
+    /// ```
+    #[must_use]
+    pub const fn error_span(&self) -> Option<Span> {
+        self.error_span
+    }
+
+    /// Returns how many times each kind of AST node was visited, by the names given by
+    /// [`AstNodes::kind`], if counting was enabled with [`Interpreter::set_stats_enabled`]. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::Interpreter;
+    /// use cara::frontend::{Lexer, Parser};
+    ///
+    /// let lexer = Lexer::new("var x = 0; for i in (0, 10) { x = x + i; }".into());
+    /// let (ast, strings) = Parser::new(lexer).parse_compile_unit().unwrap();
+    ///
+    /// let mut interpreter = Interpreter::new(strings);
+    /// interpreter.set_stats_enabled(true);
+    /// interpreter.visit(&ast).unwrap();
+    /// let stats = interpreter.stats().unwrap();
+    /// assert_eq!(stats["For"], 1);
+    /// assert_eq!(stats["BinaryOp"], 10);
+    /// assert_eq!(stats["Assign"], 10);
+    /// ```
+    #[must_use]
+    pub const fn stats(&self) -> Option<&BTreeMap<&'static str, u64>> {
+        self.stats.as_ref()
+    }
+
+    /// Stops scripts from calling the builtin `name`, such as `eval`. A function with
+    /// that name is called instead if there is one. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::{Error, Interpreter};
+    /// use cara::frontend::{Lexer, Parser};
+    ///
+    /// let lexer = Lexer::new(r#"eval("1 + 1")"#.into());
+    /// let (ast, strings) = Parser::new(lexer).parse_compile_unit().unwrap();
+    ///
+    /// let mut interpreter = Interpreter::new(strings);
+    /// interpreter.disable_builtin("eval");
+    /// assert!(matches!(interpreter.visit(&ast), Err(Error::SymbolNotFound(_))));
+    /// ```
+    pub fn disable_builtin(&mut self, name: &str) {
+        self.disabled_builtins.insert(name.into());
+    }
+
+    /// Adds a pass to the ones [`Interpreter::prepare`] runs, after those added before.
+    pub fn add_pass(&mut self, pass: Pass) {
+        self.passes.push(pass);
+    }
+
+    /// Runs every registered pass over `ast` in order. \
+    /// Example
+    /// ```rust
+    /// use cara::ast::{AstNodes, Op};
+    /// use cara::backend::Interpreter;
+    /// use cara::frontend::{Lexer, Parser};
+    ///
+    /// // Rewrites `x + 0` into `x` in variable definitions.
+    /// fn strip_add_zero(node: AstNodes) -> AstNodes {
+    ///     match node {
+    ///         AstNodes::CompileUnit(statements) => {
+    ///             AstNodes::CompileUnit(statements.into_iter().map(strip_add_zero).collect())
+    ///         }
+    ///         AstNodes::VarDef(id, value) => {
+    ///             AstNodes::VarDef(id, strip_add_zero(value.as_ref().clone()).into())
+    ///         }
+    ///         AstNodes::BinaryOp(left, Op::Add, right, _)
+    ///             if matches!(right.as_ref(), AstNodes::Number(n) if *n == 0.into()) =>
+    ///         {
+    ///             left.as_ref().clone()
+    ///         }
+    ///         node => node,
+    ///     }
+    /// }
+    ///
+    /// let lexer = Lexer::new("var y = x + 0;".into());
+    /// let (ast, strings) = Parser::new(lexer).parse_compile_unit().unwrap();
+    ///
+    /// let mut interpreter = Interpreter::new(strings);
+    /// interpreter.add_pass(Box::new(strip_add_zero));
+    /// let AstNodes::CompileUnit(statements) = interpreter.prepare(ast) else { unreachable!() };
+    /// let AstNodes::VarDef(_, value) = &statements[0] else { unreachable!() };
+    /// assert!(matches!(value.as_ref(), AstNodes::ReadVar(_)));
+    /// ```
+    #[must_use]
+    pub fn prepare(&self, ast: AstNodes) -> AstNodes {
+        self.passes.iter().fold(ast, |ast, pass| pass(ast))
+    }
+
+    /// Registers a native function which scripts can call by `name`.
+    /// Functions defined by the script take precedence over it. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::Interpreter;
+    /// use cara::frontend::{Lexer, Parser};
+    ///
+    /// let lexer = Lexer::new("if double(21) != 42 { fail(); }".into());
+    /// let (ast, strings) = Parser::new(lexer).parse_compile_unit().unwrap();
+    ///
+    /// let mut interpreter = Interpreter::new(strings);
+    /// interpreter.register_fn("double", |args| {
+    ///     let number = args[0].as_int()?;
+    ///     Ok(cara::backend::CrValue::Number(number + number))
+    /// });
+    /// assert!(interpreter.visit(&ast).is_ok());
+    /// ```
+    pub fn register_fn<F>(&mut self, name: &str, function: F)
+    where
+        F: Fn(&[CrValue]) -> Result<CrValue> + 'static,
+    {
+        self.natives.insert(name.into(), Box::new(function));
     }
 }
 
@@ -45,69 +495,156 @@ impl Interpreter {
     /// Visits the AST node with the visitor mode. \
     /// Example
     /// ```rust
-    /// use cara::backend::Interpreter;
+    /// use cara::backend::{CrValue, Interpreter};
     /// use cara::frontend::{Parser,Lexer};
     ///
     /// let mut lexer = Lexer::new("1+1".into());
     /// let mut parser = Parser::new(lexer);
-    /// let node = parser.parse_compile_unit();
+    /// let (node, strings) = parser.parse_compile_unit().unwrap();
     ///
-    /// let mut interpreter = Interpreter::new();
-    /// assert_eq!(interpreter.visit(node),2);
+    /// let mut interpreter = Interpreter::new(strings);
+    /// assert_eq!(interpreter.visit(&node).unwrap(), CrValue::Number(2.into()));
     /// ```
     #[inline]
     pub fn visit(&mut self, node: &AstNodes) -> Result<CrValue> {
+        if let Some(steps) = &mut self.steps_left {
+            if *steps == 0 {
+                return Err(Error::StepLimitExceeded);
+            }
+            *steps -= 1;
+        }
+        if let Some(stats) = &mut self.stats {
+            *stats.entry(node.kind()).or_default() += 1;
+        }
+        #[cfg(feature = "std")]
+        if let Some(deadline) = self.deadline {
+            self.ticks += 1;
+            if self.ticks == DEADLINE_CHECK_INTERVAL {
+                self.ticks = 0;
+                if Instant::now() >= deadline {
+                    return Err(Error::Timeout);
+                }
+            }
+        }
+
         match node {
-            AstNodes::Assign(id, index, value) => self.visit_assign(*id, index.as_ref(), value),
-            AstNodes::BinaryOp(left, op, right) => self.visit_binary_op(left, op, right),
-            AstNodes::CompileUnit(statements) => self.visit_compile_unit(statements),
+            AstNodes::Assign(id, indices, value) => self.visit_assign(*id, indices, value),
+            AstNodes::BinaryOp(left, op, right, span) => {
+                self.visit_binary_op(left, op, right, *span)
+            }
+            AstNodes::CompileUnit(statements) => {
+                self.error_span = None;
+                match self.visit_compile_unit(statements) {
+                    Err(Error::Break(_) | Error::Continue) => Err(Error::NotInLoop),
+                    result => result,
+                }
+            }
             AstNodes::Number(num) => Ok(CrValue::Number(num.clone())),
+            AstNodes::Float(value) => Ok(CrValue::Float(*value)),
+            AstNodes::Bool(value) => Ok(CrValue::Bool(*value)),
+            AstNodes::Str(id) => Ok(CrValue::Str(self.symbol_tables.name(*id).into())),
+            AstNodes::Char(ch) => Ok(CrValue::Char(*ch)),
             AstNodes::UnaryOp(op, val) => self.visit_unary_op(op, val),
             AstNodes::VarDef(id, init_value) => self.visit_var_def(*id, init_value),
+            AstNodes::Unpack(ids, value) => self.visit_unpack(ids, value),
+            AstNodes::VarUnpack(ids, value) => self.visit_var_unpack(ids, value),
             AstNodes::ConstDef(id, const_value) => self.visit_const_def(*id, const_value),
             AstNodes::ReadVar(id) => self.visit_read_var(*id),
             AstNodes::FunctionDef(id, params, body) => self.visit_function_def(*id, params, body),
-            AstNodes::Call(id, args) => self.visit_call(*id, args),
+            AstNodes::Lambda(params, body) => Ok(self.make_function(params, body)),
+            AstNodes::Call(callee, args) => self.visit_call(callee, args),
             AstNodes::Return(value) => self.visit_return(value),
+            AstNodes::Ternary(condition, then_value, else_value) => {
+                if self.visit(condition)?.is_truthy()? {
+                    self.visit(then_value)
+                } else {
+                    self.visit(else_value)
+                }
+            }
             AstNodes::If(condition, then_block, else_block) => {
                 self.visit_if(condition, then_block, else_block)
             }
             AstNodes::For(variable, start, end, step, body) => {
                 self.visit_for(*variable, start, end, step, body)
             }
+            AstNodes::ForEach(variable, list, body) => self.visit_for_each(*variable, list, body),
             AstNodes::List(value_list) => self.visit_list(value_list),
-            AstNodes::Index(id, index) => self.visit_index(*id, index),
+            AstNodes::Map(entries) => self.visit_map(entries),
+            AstNodes::Index(list, index, span) => self.visit_index(list, index, *span),
             AstNodes::TemplateList(template, num) => self.visit_template_list(template, num),
             AstNodes::While(condition, body) => self.visit_while(condition, body),
-            AstNodes::Break => Err(Error::Break),
+            AstNodes::Loop(body) => self.visit_loop(body),
+            AstNodes::Break(value) => {
+                let value = value.as_ref().map(|value| self.visit(value)).transpose()?;
+                Err(Error::Break(value))
+            }
             AstNodes::Continue => Err(Error::Continue),
         }
     }
 
+    /// Visits the AST node like [`Interpreter::visit`], but gives up with
+    /// [`Error::Timeout`] once it has run for longer than `timeout`. \
+    /// Example
+    /// ```rust
+    /// use std::time::{Duration, Instant};
+    ///
+    /// use cara::backend::{Error, Interpreter};
+    /// use cara::frontend::{Lexer, Parser};
+    ///
+    /// let lexer = Lexer::new("while true { }".into());
+    /// let (ast, strings) = Parser::new(lexer).parse_compile_unit().unwrap();
+    ///
+    /// let mut interpreter = Interpreter::new(strings);
+    /// let start = Instant::now();
+    /// let result = interpreter.visit_with_timeout(&ast, Duration::from_millis(50));
+    /// assert!(matches!(result, Err(Error::Timeout)));
+    /// assert!(start.elapsed() < Duration::from_secs(1));
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn visit_with_timeout(&mut self, node: &AstNodes, timeout: Duration) -> Result<CrValue> {
+        self.deadline = Some(Instant::now() + timeout);
+        self.ticks = 0;
+        let result = self.visit(node);
+        self.deadline = None;
+        result
+    }
+
     #[inline]
     fn with_block<F, R>(&mut self, f: F) -> R
     where
         F: FnOnce(&mut Self) -> R,
     {
         let cur_index = self.symbol_tables.len();
-        self.symbol_tables.0.push(SymbolTable::new());
+        self.symbol_tables.push();
 
         let result = f(self);
 
         debug_assert_eq!(self.symbol_tables.len(), cur_index + 1);
-        self.symbol_tables.0.pop().unwrap();
+        self.symbol_tables.pop();
+        result
+    }
+
+    /// Runs `f` in a new scope which only sees the global scope besides itself.
+    #[inline]
+    fn with_function_scope<F, R>(&mut self, f: F) -> R
+    where
+        F: FnOnce(&mut Self) -> R,
+    {
+        let caller = self.symbol_tables.hide_locals();
+        let result = self.with_block(f);
+        self.symbol_tables.show_locals(caller);
         result
     }
 
     fn visit_while(&mut self, condition: &Rc<AstNodes>, body: &[AstNodes]) -> Result<CrValue> {
         self.with_block(|this| {
-            while *this.visit(condition)?.as_int()? > IBig::ZERO {
+            while this.visit(condition)?.is_truthy()? {
                 this.symbol_tables.clear_last();
 
                 for item in body {
                     match this.visit(item) {
                         Ok(_) => (),
-                        Err(Error::Break) => return Ok(CrValue::Void),
+                        Err(Error::Break(value)) => return Ok(value.unwrap_or(CrValue::Void)),
                         Err(Error::Continue) => break,
                         Err(e) => return Err(e),
                     }
@@ -118,11 +655,53 @@ impl Interpreter {
         })
     }
 
+    fn visit_loop(&mut self, body: &[AstNodes]) -> Result<CrValue> {
+        self.with_block(|this| loop {
+            this.symbol_tables.clear_last();
+
+            for item in body {
+                match this.visit(item) {
+                    Ok(_) => (),
+                    Err(Error::Break(value)) => return Ok(value.unwrap_or(CrValue::Void)),
+                    Err(Error::Continue) => break,
+                    Err(e) => return Err(e),
+                }
+            }
+        })
+    }
+
     #[inline]
-    fn visit_index(&mut self, id: usize, index: &Rc<AstNodes>) -> Result<CrValue> {
-        let number = self.visit(index)?;
-        let index = usize::try_from(number.as_int()?).unwrap();
-        self.symbol_tables.symbol_crvalue_list_item(id, index)
+    fn visit_index(
+        &mut self,
+        list: &Rc<AstNodes>,
+        index: &Rc<AstNodes>,
+        span: Span,
+    ) -> Result<CrValue> {
+        let index = self.visit(index)?;
+        let item = if let AstNodes::ReadVar(id) = list.as_ref() {
+            // Reads the element in place rather than copying the whole list.
+            self.symbol_tables.symbol_crvalue_item(*id, &index)
+        } else {
+            self.visit(list)?.item(&index)
+        };
+        self.locate(item, span)
+    }
+
+    /// Remembers `span` as the position of the error if `result` is one.
+    fn locate<T>(&mut self, result: Result<T>, span: Span) -> Result<T> {
+        if result.is_err() {
+            self.error_span = Some(span);
+        }
+        result
+    }
+
+    fn visit_map(&mut self, entries: &[(AstNodes, AstNodes)]) -> Result<CrValue> {
+        let mut map = BTreeMap::new();
+        for (key, value) in entries {
+            let key = MapKey::try_from(self.visit(key)?)?;
+            map.insert(key, self.visit(value)?);
+        }
+        Ok(CrValue::Map(map))
     }
 
     #[inline]
@@ -133,7 +712,10 @@ impl Interpreter {
     ) -> Result<CrValue> {
         let template_value = self.visit(template)?;
         let number = self.visit(size)?;
-        let size = usize::try_from(number.as_int()?).unwrap();
+        let size = list_index(number.as_int()?)?;
+        if self.max_list_len.is_some_and(|max| size > max) {
+            return Err(Error::ListTooLong);
+        }
         Ok(CrValue::List(vec![template_value; size]))
     }
 
@@ -158,22 +740,53 @@ impl Interpreter {
         let end = self.visit(end)?;
         let step = self.visit(step)?;
 
-        let start = isize::try_from(start.as_int()?).unwrap();
-        let end = isize::try_from(end.as_int()?).unwrap();
-        let step = usize::try_from(step.as_int()?).unwrap();
+        let mut number = start.as_int()?.clone();
+        let end = end.as_int()?.clone();
+        let step = step.as_int()?.clone();
+        if step == IBig::ZERO {
+            return Err(Error::ZeroStep);
+        }
 
         self.with_block(|this| {
-            for number in (start..end).step_by(step) {
+            while (step > IBig::ZERO && number < end) || (step < IBig::ZERO && number > end) {
                 this.symbol_tables.clear_last();
 
-                let number = IBig::from(number);
-                let value = Symbol::Const(variable.to_owned(), CrValue::Number(number));
+                let value = Symbol::Var(variable.to_owned(), CrValue::Number(number.clone()));
                 this.symbol_tables.insert_sym(value);
+                number += &step;
+
+                for item in body {
+                    match this.visit(item) {
+                        Ok(_) => (),
+                        Err(Error::Break(value)) => return Ok(value.unwrap_or(CrValue::Void)),
+                        Err(Error::Continue) => break,
+                        Err(e) => return Err(e),
+                    }
+                }
+            }
+            Ok(CrValue::Void)
+        })
+    }
+
+    fn visit_for_each(
+        &mut self,
+        variable: usize,
+        list: &Rc<AstNodes>,
+        body: &[AstNodes],
+    ) -> Result<CrValue> {
+        let list = self.visit(list)?;
+        let values = list.as_list()?.clone();
+
+        self.with_block(|this| {
+            for value in values {
+                this.symbol_tables.clear_last();
+                this.symbol_tables
+                    .insert_sym(Symbol::Var(variable.to_owned(), value));
 
                 for item in body {
                     match this.visit(item) {
                         Ok(_) => (),
-                        Err(Error::Break) => return Ok(CrValue::Void),
+                        Err(Error::Break(value)) => return Ok(value.unwrap_or(CrValue::Void)),
                         Err(Error::Continue) => break,
                         Err(e) => return Err(e),
                     }
@@ -191,7 +804,7 @@ impl Interpreter {
     ) -> Result<CrValue> {
         let condition = self.visit(condition)?;
         self.with_block(|this| {
-            if *condition.as_int()? > IBig::ZERO {
+            if condition.is_truthy()? {
                 this.visit_compile_unit(then_block)
             } else {
                 this.visit_compile_unit(else_block)
@@ -202,16 +815,18 @@ impl Interpreter {
     fn visit_assign(
         &mut self,
         id: usize,
-        index: Option<&Rc<AstNodes>>,
+        indices: &[AstNodes],
         value: &Rc<AstNodes>,
     ) -> Result<CrValue> {
         let value = self.visit(value)?;
-        if let Some(index) = index {
-            let number = self.visit(index)?;
-            let index = usize::try_from(number.as_int()?).unwrap();
-            self.symbol_tables.symbol_list_modify(id, index, value)?;
-        } else {
+        if indices.is_empty() {
             self.symbol_tables.symbol_assign(id, value)?;
+        } else {
+            let path = indices
+                .iter()
+                .map(|index| self.visit(index))
+                .collect::<Result<Vec<CrValue>>>()?;
+            self.symbol_tables.symbol_modify_path(id, &path, value)?;
         }
         Ok(CrValue::Void)
     }
@@ -221,43 +836,164 @@ impl Interpreter {
         left: &Rc<AstNodes>,
         op: &Op,
         right: &Rc<AstNodes>,
+        span: Span,
     ) -> Result<CrValue> {
         let left = self.visit(left)?;
-        let left = left.as_int()?;
         let right = self.visit(right)?;
-        let right = right.as_int()?;
-
-        Ok(CrValue::Number(match op {
-            Op::Add => left + right,
-            Op::Sub => left - right,
-            Op::Mul => left * right,
-            Op::Div => left / right,
-            Op::Eq => IBig::from(u8::from(left == right)),
-            Op::Ne => IBig::from(u8::from(left != right)),
-            Op::Le => IBig::from(u8::from(left <= right)),
-            Op::Ge => IBig::from(u8::from(left >= right)),
-            Op::Lt => IBig::from(u8::from(left < right)),
-            Op::Gt => IBig::from(u8::from(left > right)),
-            Op::Or => IBig::from(u8::from(*left > IBig::ZERO || *right > IBig::ZERO)),
-            Op::And => IBig::from(u8::from(*left > IBig::ZERO && *right > IBig::ZERO)),
-            Op::Rem => left % right,
-            Op::LShift => left << usize::try_from(right).unwrap(),
-            Op::RShift => left >> usize::try_from(right).unwrap(),
-        }))
+        let result = self.apply_binary_op(left, op, right);
+        self.locate(result, span)
+    }
+
+    fn apply_binary_op(&mut self, left: CrValue, op: &Op, right: CrValue) -> Result<CrValue> {
+        match op {
+            Op::Eq => return Ok(CrValue::Bool(left == right)),
+            Op::Ne => return Ok(CrValue::Bool(left != right)),
+            Op::Or => return Ok(CrValue::Bool(left.is_truthy()? || right.is_truthy()?)),
+            Op::And => return Ok(CrValue::Bool(left.is_truthy()? && right.is_truthy()?)),
+            _ => {}
+        }
+
+        if let (Op::Add, CrValue::List(left), CrValue::List(right)) = (op, &left, &right) {
+            if self
+                .max_list_len
+                .is_some_and(|max| left.len().saturating_add(right.len()) > max)
+            {
+                return Err(Error::ListTooLong);
+            }
+            return Ok(CrValue::List([left.as_slice(), right].concat()));
+        }
+
+        if let CrValue::Str(string) = &left {
+            return match (op, &right) {
+                (Op::Add, CrValue::Str(right)) => Ok(CrValue::Str(string.clone() + right)),
+                (Op::Mul, CrValue::Number(count)) => self.repeat_str(string, count),
+                _ => Err(Error::NonIntCalc),
+            };
+        }
+
+        if let (CrValue::Char(left), CrValue::Char(right)) = (&left, &right) {
+            return match op {
+                Op::Le => Ok(CrValue::Bool(left <= right)),
+                Op::Ge => Ok(CrValue::Bool(left >= right)),
+                Op::Lt => Ok(CrValue::Bool(left < right)),
+                Op::Gt => Ok(CrValue::Bool(left > right)),
+                _ => Err(Error::NonIntCalc),
+            };
+        }
+
+        if let (CrValue::Float(_), CrValue::Number(_) | CrValue::Float(_))
+        | (CrValue::Number(_), CrValue::Float(_)) = (&left, &right)
+        {
+            return Self::visit_float_op(left.as_float().unwrap(), *op, right.as_float().unwrap());
+        }
+
+        let (CrValue::Number(left), CrValue::Number(right)) = (&left, &right) else {
+            return Err(match (left, right) {
+                (CrValue::Void, _) | (_, CrValue::Void) => Error::UseVoidValue,
+                _ => Error::NonIntCalc,
+            });
+        };
+
+        Ok(match op {
+            Op::Add => CrValue::Number(left + right),
+            Op::Sub => CrValue::Number(left - right),
+            Op::Mul => {
+                self.check_int_bits(left.bit_len().saturating_add(right.bit_len()))?;
+                CrValue::Number(left * right)
+            }
+            Op::Div | Op::Rem if *right == IBig::ZERO => return Err(Error::DivByZero),
+            Op::Div => CrValue::Number(left / right),
+            Op::Le => CrValue::Bool(left <= right),
+            Op::Ge => CrValue::Bool(left >= right),
+            Op::Lt => CrValue::Bool(left < right),
+            Op::Gt => CrValue::Bool(left > right),
+            Op::Rem => CrValue::Number(left % right),
+            Op::LShift => {
+                let amount = Self::shift_amount(right, MAX_LEFT_SHIFT)?;
+                self.check_int_bits(left.bit_len().saturating_add(amount))?;
+                CrValue::Number(left << amount)
+            }
+            Op::RShift => CrValue::Number(left >> Self::shift_amount(right, usize::MAX)?),
+            Op::Pow => CrValue::Number(self.int_pow(left, right)?),
+            Op::BitAnd => CrValue::Number(left & right),
+            Op::BitOr => CrValue::Number(left | right),
+            Op::BitXor => CrValue::Number(left ^ right),
+            // Only a custom precedence table can make `!` a binary operator.
+            Op::Not => return Err(Error::NonIntCalc),
+            Op::Eq | Op::Ne | Op::Or | Op::And => unreachable!(),
+        })
+    }
+
+    /// Repeats a string for `"ab" * 3`. The length of the result is bounded like
+    /// template lists.
+    fn repeat_str(&self, string: &str, count: &IBig) -> Result<CrValue> {
+        let count = usize::try_from(count).map_err(|_| Error::InvalidArrayLen)?;
+        if self
+            .max_list_len
+            .is_some_and(|max| string.len().saturating_mul(count) > max)
+        {
+            return Err(Error::ListTooLong);
+        }
+        Ok(CrValue::Str(string.repeat(count)))
+    }
+
+    fn visit_float_op(left: f64, op: Op, right: f64) -> Result<CrValue> {
+        Ok(match op {
+            Op::Add => CrValue::Float(left + right),
+            Op::Sub => CrValue::Float(left - right),
+            Op::Mul => CrValue::Float(left * right),
+            Op::Div => CrValue::Float(left / right),
+            Op::Rem => CrValue::Float(left % right),
+            Op::Pow => CrValue::Float(float_pow(left, right)?),
+            Op::Le => CrValue::Bool(left <= right),
+            Op::Ge => CrValue::Bool(left >= right),
+            Op::Lt => CrValue::Bool(left < right),
+            Op::Gt => CrValue::Bool(left > right),
+            _ => return Err(Error::NonIntCalc),
+        })
+    }
+
+    /// Checks the right operand of a shift. Negative amounts are an error rather than
+    /// a shift in the other direction.
+    fn shift_amount(amount: &IBig, max: usize) -> Result<usize> {
+        usize::try_from(amount)
+            .ok()
+            .filter(|amount| *amount <= max)
+            .ok_or(Error::BadShift)
+    }
+
+    /// Raises `base` to `exp`, which can't be negative.
+    pub(super) fn int_pow(&self, base: &IBig, exp: &IBig) -> Result<IBig> {
+        let exp = usize::try_from(exp).map_err(|_| Error::NonIntCalc)?;
+        // The result has at least this many bits.
+        self.check_int_bits(base.bit_len().saturating_sub(1).saturating_mul(exp))?;
+        Ok(base.pow(exp))
+    }
+
+    /// Fails if a result of up to `bits` bits would exceed the configured bound.
+    pub(super) fn check_int_bits(&self, bits: usize) -> Result<()> {
+        match self.max_int_bits {
+            Some(max) if bits > max => Err(Error::IntTooLarge),
+            _ => Ok(()),
+        }
     }
 
     #[inline]
     fn visit_compile_unit(&mut self, statements: &[AstNodes]) -> Result<CrValue> {
         statements
             .iter()
-            .map(|item| self.visit(item))
-            .collect::<Result<Vec<CrValue>>>()?;
-        Ok(CrValue::Void)
+            .try_fold(CrValue::Void, |_, item| self.visit(item))
     }
 
     #[inline]
     fn visit_unary_op(&mut self, op: &Op, val: &Rc<AstNodes>) -> Result<CrValue> {
         let value = self.visit(val)?;
+        if *op == Op::Not {
+            return Ok(CrValue::Bool(!value.is_truthy()?));
+        }
+        if let CrValue::Float(value) = value {
+            return Ok(CrValue::Float(if *op == Op::Sub { -value } else { value }));
+        }
         let result = match op {
             Op::Sub => -value.as_int()?,
             _ => value.as_int()?.clone(),
@@ -269,7 +1005,7 @@ impl Interpreter {
     fn visit_const_def(&mut self, id: usize, const_value: &Rc<AstNodes>) -> Result<CrValue> {
         let const_value = self.visit(const_value)?;
         self.symbol_tables
-            .insert_sym(Symbol::Const(id.to_owned(), const_value));
+            .define_sym(Symbol::Const(id.to_owned(), const_value))?;
         Ok(CrValue::Void)
     }
 
@@ -277,16 +1013,66 @@ impl Interpreter {
     fn visit_var_def(&mut self, id: usize, init_value: &Rc<AstNodes>) -> Result<CrValue> {
         let init_value = self.visit(init_value)?;
         self.symbol_tables
-            .insert_sym(Symbol::Var(id.to_owned(), init_value));
+            .define_sym(Symbol::Var(id.to_owned(), init_value))?;
         Ok(CrValue::Void)
     }
 
+    fn visit_unpack(&mut self, ids: &[usize], value: &Rc<AstNodes>) -> Result<CrValue> {
+        let values = self.unpack_values(ids.len(), value)?;
+        for (id, value) in zip(ids, values) {
+            self.symbol_tables.symbol_assign(*id, value)?;
+        }
+        Ok(CrValue::Void)
+    }
+
+    fn visit_var_unpack(&mut self, ids: &[usize], value: &Rc<AstNodes>) -> Result<CrValue> {
+        let values = self.unpack_values(ids.len(), value)?;
+        for (id, value) in zip(ids, values) {
+            self.symbol_tables.define_sym(Symbol::Var(*id, value))?;
+        }
+        Ok(CrValue::Void)
+    }
+
+    /// Evaluates the right side of an assignment to `count` variables.
+    fn unpack_values(&mut self, count: usize, value: &Rc<AstNodes>) -> Result<Vec<CrValue>> {
+        let values = match self.visit(value)? {
+            CrValue::List(values) => values,
+            CrValue::Frozen(values) => values.as_ref().clone(),
+            _ => return Err(Error::UnpackMismatch),
+        };
+        if values.len() != count {
+            return Err(Error::UnpackMismatch);
+        }
+        Ok(values)
+    }
+
     #[inline]
     fn visit_read_var(&self, id: usize) -> Result<CrValue> {
         let value = self.symbol_tables.symbol_clone_value(id)?;
         Ok(value)
     }
 
+    /// Defines a function, which is a constant, so it can't share its name with
+    /// another definition in the same scope. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::Error;
+    /// use cara::CaraError;
+    ///
+    /// for src in [
+    ///     "const f = 1; fn f() { return 2; } f()",
+    ///     "fn f() { return 2; } const f = 1; f()",
+    ///     "var f = 1; fn f() {}",
+    ///     "fn f() {} var f = 2;",
+    ///     "fn f() {} fn f() {}",
+    /// ] {
+    ///     let error = cara::eval(src).unwrap_err();
+    ///     assert!(matches!(error, CaraError::Runtime(Error::DuplicatedDef)), "{src}");
+    /// }
+    ///
+    /// let src = "fn f() { return 1; } fn g() { fn f() { return 2; } return f(); } [f(), g()]";
+    /// assert_eq!(cara::eval(src).unwrap(), cara::eval("[1, 2]").unwrap());
+    /// ```
     #[inline]
     fn visit_function_def(
         &mut self,
@@ -294,22 +1080,83 @@ impl Interpreter {
         params: &[usize],
         body: &[AstNodes],
     ) -> Result<CrValue> {
-        let symbol = Symbol::Function(
-            id.to_owned(),
+        let function = self.make_function(params, body);
+        self.symbol_tables.define_sym(Symbol::Const(id, function))?;
+        Ok(CrValue::Void)
+    }
+
+    /// Creates a function which keeps a snapshot of the locals it can see, so it
+    /// still works after the scope defining it is gone.
+    fn make_function(&self, params: &[usize], body: &[AstNodes]) -> CrValue {
+        CrValue::Function(
             params.to_owned().into(),
             body.to_vec().into(),
-        );
-        self.symbol_tables.insert_sym(symbol);
-        Ok(CrValue::Void)
+            self.symbol_tables.capture_locals().into(),
+        )
     }
 
-    fn visit_call(&mut self, id: usize, args: &[AstNodes]) -> Result<CrValue> {
-        match self.string_table[id].as_str() {
-            "print" => {
-                self.print(args)?;
+    fn visit_call(&mut self, callee: &AstNodes, args: &[AstNodes]) -> Result<CrValue> {
+        if let AstNodes::ReadVar(id) = callee {
+            return self.visit_named_call(*id, args);
+        }
+
+        let tail_call = mem::take(&mut self.tail_call);
+        let function = self.visit(callee)?;
+        if !function.is_callable() {
+            return Err(Error::NotCallable(function.to_string()));
+        }
+        let args = args
+            .iter()
+            .map(|arg| self.visit(arg))
+            .collect::<Result<Vec<CrValue>>>()?;
+
+        if tail_call && self.is_running(&function) {
+            return Err(Error::TailCall(args));
+        }
+        self.call_value(function, args)
+    }
+
+    /// Calls a function by name, which may be a builtin. A symbol defined by the
+    /// script hides a builtin of the same name. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::CrValue;
+    ///
+    /// let src = "fn len(list) { return 42; } len([1, 2])";
+    /// assert_eq!(cara::eval(src).unwrap(), CrValue::Number(42.into()));
+    /// let src = "fn f() { var max = fn(a, b) { return a; }; return max(1, 2); } [f(), max([1, 2])]";
+    /// assert_eq!(cara::eval(src).unwrap(), cara::eval("[1, 2]").unwrap());
+    /// let src = "const map = fn(list, f) { return 0; }; map([1], fn(x) { return x; })";
+    /// assert_eq!(cara::eval(src).unwrap(), CrValue::Number(0.into()));
+    /// let src = "fn abs(n) { return n; } [abs(-1), gcd(4, 6)]";
+    /// assert_eq!(cara::eval(src).unwrap(), cara::eval("[-1, 2]").unwrap());
+    /// ```
+    fn visit_named_call(&mut self, id: usize, args: &[AstNodes]) -> Result<CrValue> {
+        let tail_call = mem::take(&mut self.tail_call);
+
+        let name = self.symbol_tables.name(id);
+        let builtin = if self.disabled_builtins.contains(name) || self.symbol_tables.contains(id) {
+            ""
+        } else {
+            name
+        };
+        match builtin {
+            "print" | "println" => {
+                self.print(args, true)?;
+                return Ok(CrValue::Void);
+            }
+            "input" => {
+                return self.input(args);
+            }
+            "write" => {
+                self.print(args, false)?;
                 return Ok(CrValue::Void);
             }
-            "append" => {
+            "printf" => {
+                self.printf(args)?;
+                return Ok(CrValue::Void);
+            }
+            "append" | "push" => {
                 self.append(args)?;
                 return Ok(CrValue::Void);
             }
@@ -317,43 +1164,293 @@ impl Interpreter {
                 self.insert(args)?;
                 return Ok(CrValue::Void);
             }
+            "get" => {
+                return self.get(args);
+            }
+            "set" => {
+                self.set(args)?;
+                return Ok(CrValue::Void);
+            }
+            "keys" => {
+                return self.keys(args);
+            }
+            "values" => {
+                return self.values(args);
+            }
+            "typeof" => {
+                return self.type_of(args);
+            }
+            "assert" => {
+                return self.assert(args);
+            }
+            "assert_eq" => {
+                return self.assert_eq(args);
+            }
             "len" => {
                 return self.len(args);
             }
             "remove" => {
                 return self.remove(args);
             }
+            "pop" => {
+                return self.pop(args);
+            }
+            "unzip" => {
+                return self.unzip(args);
+            }
+            "repeat_with" => {
+                return self.repeat_with(args);
+            }
+            "memoize" => {
+                return self.memoize(args);
+            }
+            "freeze" => {
+                return self.freeze(args);
+            }
+            "thaw" => {
+                return self.thaw(args);
+            }
+            "ord" => {
+                return self.ord(args);
+            }
+            "str" => {
+                return self.str(args);
+            }
+            "int" => {
+                return self.int(args);
+            }
+            "chr" => {
+                return self.chr(args);
+            }
+            "chars" => {
+                return self.chars(args);
+            }
+            "lines" => {
+                return self.lines(args);
+            }
+            "cmp" => {
+                return self.cmp(args);
+            }
+            "zip_with" => {
+                return self.zip_with(args);
+            }
+            "dedup" => {
+                return self.dedup(args);
+            }
+            "rotate" => {
+                return self.rotate(args);
+            }
+            "sort" => {
+                self.sort(args)?;
+                return Ok(CrValue::Void);
+            }
+            "reverse" => {
+                self.reverse(args)?;
+                return Ok(CrValue::Void);
+            }
+            "min" => {
+                return self.min(args);
+            }
+            "max" => {
+                return self.max(args);
+            }
+            "sum" => {
+                return self.sum(args);
+            }
+            "range" => {
+                return self.range(args);
+            }
+            "pow" => {
+                return self.pow(args);
+            }
+            "modpow" => {
+                return self.modpow(args);
+            }
+            "abs" => {
+                return self.abs(args);
+            }
+            "gcd" => {
+                return self.gcd(args);
+            }
+            "lcm" => {
+                return self.lcm(args);
+            }
+            "eval" => {
+                return self.eval(args);
+            }
+            "map" => {
+                return self.map(args);
+            }
+            "filter" => {
+                return self.filter(args);
+            }
+            "reduce" => {
+                return self.reduce(args);
+            }
             _ => {}
         }
 
-        let function = self
-            .symbol_tables
-            .symbol_clone(id)
-            .unwrap_or_else(|_| panic!("Unable to find function {id}!"));
+        let args = args
+            .iter()
+            .map(|arg| self.visit(arg))
+            .collect::<Result<Vec<CrValue>>>()?;
+
+        if tail_call {
+            let function = self
+                .symbol_tables
+                .symbol_clone(id)
+                .ok()
+                .and_then(|symbol| symbol.to_callable());
+            if function.is_some_and(|function| self.is_running(&function)) {
+                return Err(Error::TailCall(args));
+            }
+        }
+        self.call_function(id, args)
+    }
+
+    /// Returns whether `function` is the one whose body is running.
+    fn is_running(&self, function: &CrValue) -> bool {
+        match (&self.running, function) {
+            (Some(running), CrValue::Function(_, body, _)) => Rc::ptr_eq(running, body),
+            _ => false,
+        }
+    }
+
+    pub(super) fn call_function(&mut self, id: usize, args: Vec<CrValue>) -> Result<CrValue> {
+        let function = match self.symbol_tables.symbol_clone(id) {
+            Ok(function) => function,
+            Err(error) => {
+                return match self.natives.get(self.symbol_tables.name(id)) {
+                    Some(native) => native(&args),
+                    None => Err(error),
+                };
+            }
+        };
+
+        match function.to_callable() {
+            Some(function) => self.call_named(function, args, Some(id)),
+            None => Err(Error::NotCallable(self.symbol_tables.name(id).into())),
+        }
+    }
+
+    pub(super) fn call_value(&mut self, function: CrValue, args: Vec<CrValue>) -> Result<CrValue> {
+        self.call_named(function, args, None)
+    }
 
+    /// Calls `function`, binding `own_name` to it inside its body when it was called by
+    /// name. A function defined in a block only captures the locals which existed
+    /// before it, so this is what lets it call itself.
+    fn call_named(
+        &mut self,
+        function: CrValue,
+        args: Vec<CrValue>,
+        own_name: Option<usize>,
+    ) -> Result<CrValue> {
         match function {
-            Symbol::Function(_, params, body) => self.with_block(|this| {
-                for (name, value) in zip(params.as_ref(), args) {
-                    let value = Symbol::Const(*name, this.visit(value)?);
-                    this.symbol_tables.insert_sym(value);
-                }
-                for item in body.as_ref() {
-                    if let Err(error) = this.visit(item) {
-                        if let Error::Return(value) = error {
-                            return Ok(value);
+            CrValue::Function(params, body, captures) => {
+                let caller = self.running.replace(body.clone());
+                let mut args = args;
+                // A self call in tail position rebinds the parameters and runs the body
+                // again here instead of growing the native stack.
+                let function = CrValue::Function(params.clone(), body.clone(), captures.clone());
+                let result = loop {
+                    if args.len() != params.len() {
+                        let name = own_name.map_or("<lambda>", |id| self.symbol_tables.name(id));
+                        break Err(Error::ArityMismatch {
+                            name: name.into(),
+                            expected: params.len(),
+                            found: args.len(),
+                        });
+                    }
+                    let result = self.with_function_scope(|this| {
+                        // Captures are the call's own copies, so the body may change them.
+                        for (name, value) in captures.iter() {
+                            this.symbol_tables
+                                .insert_sym(Symbol::Var(*name, value.clone()));
+                        }
+                        if let Some(own_name) = own_name {
+                            this.symbol_tables
+                                .insert_sym(Symbol::Const(own_name, function.clone()));
+                        }
+                        // Parameters are local variables, so the body may change them.
+                        for (name, value) in zip(params.as_ref(), args) {
+                            this.symbol_tables.insert_sym(Symbol::Var(*name, value));
+                        }
+                        for item in body.as_ref() {
+                            if let Err(error) = this.visit(item) {
+                                if let Error::Return(value) = error {
+                                    return Ok(value);
+                                }
+                                return Err(error);
+                            }
                         }
-                        return Err(error);
+                        Ok(CrValue::Void)
+                    });
+                    match result {
+                        Err(Error::TailCall(next_args)) => args = next_args,
+                        Err(Error::Break(_) | Error::Continue) => break Err(Error::NotInLoop),
+                        result => break result,
                     }
+                };
+                self.running = caller;
+                result
+            }
+            CrValue::Memoized(function, cache) => {
+                // Calls with a function among their arguments aren't cached.
+                let key: Option<Vec<_>> = args.iter().map(MemoKey::new).collect();
+                let cached = key
+                    .as_ref()
+                    .and_then(|key| cache.borrow().get(key).cloned());
+                if let Some(value) = cached {
+                    return Ok(value);
+                }
+                let value = self.call_value(function.as_ref().clone(), args)?;
+                if let Some(key) = key {
+                    cache.borrow_mut().insert(key, value.clone());
                 }
-                Ok(CrValue::Void)
-            }),
-            _ => Err(Error::SymbolNotFound(id)),
+                Ok(value)
+            }
+            _ => Err(Error::UseVoidValue),
         }
     }
 
     #[inline]
-    fn visit_return(&mut self, value: &Rc<AstNodes>) -> Result<CrValue> {
-        let val = self.visit(value)?;
-        Err(Error::Return(val))
+    fn visit_return(&mut self, value: &Option<Rc<AstNodes>>) -> Result<CrValue> {
+        let Some(value) = value else {
+            return Err(Error::Return(CrValue::Void));
+        };
+        self.tail_call = matches!(value.as_ref(), AstNodes::Call(_, _));
+        let val = self.visit(value);
+        // The call normally clears the flag, but not if evaluation failed before it.
+        self.tail_call = false;
+        Err(Error::Return(val?))
+    }
+}
+
+/// Raises `base` to `exp`. Without the standard library only whole exponents are
+/// supported.
+#[cfg(feature = "std")]
+fn float_pow(base: f64, exp: f64) -> Result<f64> {
+    Ok(base.powf(exp))
+}
+
+/// Raises `base` to `exp`. Without the standard library only whole exponents are
+/// supported.
+#[cfg(not(feature = "std"))]
+fn float_pow(base: f64, exp: f64) -> Result<f64> {
+    let whole = exp as i64;
+    if whole as f64 != exp {
+        return Err(Error::NonIntCalc);
+    }
+    let mut result = 1.0;
+    let mut square = base;
+    let mut remaining = whole.unsigned_abs();
+    while remaining > 0 {
+        if remaining & 1 == 1 {
+            result *= square;
+        }
+        square *= square;
+        remaining >>= 1;
     }
+    Ok(if whole < 0 { 1.0 / result } else { result })
 }
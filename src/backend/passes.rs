@@ -0,0 +1,61 @@
+use alloc::vec;
+
+use crate::ast::{transform_nodes, AstNodes};
+
+/// The longest template list [`fold_template_lists`] replaces, since the plain list
+/// holds a copy of the template for every element.
+const MAX_FOLDED_LEN: usize = 256;
+
+/// A pass which replaces template lists of a constant value and a small constant
+/// length, such as `[0; 8]`, with plain lists. \
+/// Example
+/// ```rust
+/// use cara::ast::AstNodes;
+/// use cara::backend::{fold_template_lists, CrValue, Interpreter};
+/// use cara::frontend::{Lexer, Parser};
+///
+/// let lexer = Lexer::new("var a = [[0; 2]; 3]; var b = [x; 3]; a".into());
+/// let (ast, strings) = Parser::new(lexer).parse_compile_unit().unwrap();
+///
+/// let mut interpreter = Interpreter::new(strings);
+/// interpreter.add_pass(Box::new(fold_template_lists));
+/// let ast = interpreter.prepare(ast);
+/// let AstNodes::CompileUnit(statements) = &ast else { unreachable!() };
+/// let AstNodes::VarDef(_, value) = &statements[0] else { unreachable!() };
+/// let AstNodes::List(rows) = value.as_ref() else { unreachable!() };
+/// assert_eq!(rows.len(), 3);
+/// assert!(matches!(&rows[0], AstNodes::List(row) if row.len() == 2));
+/// let AstNodes::VarDef(_, value) = &statements[1] else { unreachable!() };
+/// assert!(matches!(value.as_ref(), AstNodes::TemplateList(..)));
+///
+/// interpreter.set_variable("x", CrValue::Number(1.into()));
+/// assert_eq!(interpreter.visit(&ast).unwrap(), cara::eval("[[0, 0], [0, 0], [0, 0]]").unwrap());
+/// ```
+#[must_use]
+pub fn fold_template_lists(ast: AstNodes) -> AstNodes {
+    transform_nodes(ast, &mut |node| match node {
+        AstNodes::TemplateList(template, size) => match size.as_ref() {
+            AstNodes::Number(len) if is_constant(&template) => match usize::try_from(len) {
+                Ok(len) if len <= MAX_FOLDED_LEN => {
+                    AstNodes::List(vec![template.as_ref().clone(); len])
+                }
+                _ => AstNodes::TemplateList(template, size),
+            },
+            _ => AstNodes::TemplateList(template, size),
+        },
+        node => node,
+    })
+}
+
+/// Returns whether the node always evaluates to the same value.
+fn is_constant(node: &AstNodes) -> bool {
+    match node {
+        AstNodes::Number(_)
+        | AstNodes::Float(_)
+        | AstNodes::Bool(_)
+        | AstNodes::Str(_)
+        | AstNodes::Char(_) => true,
+        AstNodes::List(values) => values.iter().all(is_constant),
+        _ => false,
+    }
+}
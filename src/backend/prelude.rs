@@ -0,0 +1,20 @@
+//! Library functions written in Cara, loaded by [`Interpreter::with_prelude`](super::Interpreter::with_prelude).
+
+pub(super) const PRELUDE: &str = r"
+fn contains(list, value) {
+    for i in (0, len(list)) {
+        if list[i] == value {
+            return true;
+        }
+    }
+    return false;
+}
+
+fn product(list) {
+    var result = 1;
+    for i in (0, len(list)) {
+        result = result * list[i];
+    }
+    return result;
+}
+";
@@ -1,24 +1,223 @@
+use alloc::{string::String, vec::Vec};
 use core::fmt;
 
 use super::value::CrValue;
+use crate::frontend::ParseError;
 
 /// Error returned by IR generator.
 pub enum Error {
+    /// A `var` or `const` defining a name already given to a constant in the same
+    /// scope, or a `const` defining the name of a variable. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::{CrValue, Error};
+    /// use cara::CaraError;
+    ///
+    /// let error = cara::eval("const x = 1; var x = 2;").unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::DuplicatedDef)));
+    /// let error = cara::eval("var x = 1; const x = 2;").unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::DuplicatedDef)));
+    /// // Variables may be defined again, and inner scopes may shadow constants.
+    /// let value = cara::eval("var x = 1; var x = 2; const y = 3; if true { var y = 4; } x + y");
+    /// assert_eq!(value.unwrap(), CrValue::Number(5.into()));
+    /// ```
     DuplicatedDef,
-    SymbolNotFound(usize),
+    /// A name which no variable, constant or function is defined with. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::Error;
+    /// use cara::CaraError;
+    ///
+    /// let error = cara::eval("nope();").unwrap_err();
+    /// assert!(matches!(&error, CaraError::Runtime(Error::SymbolNotFound(name)) if name == "nope"));
+    /// assert!(error.to_string().contains("nope"));
+    /// let error = cara::eval("undefined_var + 1").unwrap_err();
+    /// assert_eq!(error.to_string(), "runtime error: symbol not found: undefined_var");
+    /// ```
+    SymbolNotFound(String),
+    NotCallable(String),
     FailedToEval,
+    /// A negative length for a repeated string or list. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::Error;
+    /// use cara::CaraError;
+    ///
+    /// let error = cara::eval(r#""ab" * -1"#).unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::InvalidArrayLen)));
+    /// ```
     InvalidArrayLen,
+    IndexOutOfRange,
+    /// Taking an element out of an empty list. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::{CrValue, Error};
+    /// use cara::CaraError;
+    ///
+    /// let value = cara::eval("var s = []; push(s, 1); push(s, 2); [pop(s), pop(s)]").unwrap();
+    /// assert_eq!(value, cara::eval("[2, 1]").unwrap());
+    /// let error = cara::eval("var s = []; pop(s)").unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::EmptyList)));
+    /// ```
+    EmptyList,
+    /// A range or loop which counts by zero. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::Error;
+    /// use cara::CaraError;
+    ///
+    /// assert_eq!(cara::eval("range(5, 0, -2)").unwrap(), cara::eval("[5, 3, 1]").unwrap());
+    /// let error = cara::eval("range(0, 5, 0)").unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::ZeroStep)));
+    /// ```
+    ZeroStep,
+    DivByZero,
+    /// Source given to `eval` which doesn't parse. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::{CrValue, Error};
+    /// use cara::CaraError;
+    ///
+    /// let value = cara::eval(r#"var x = 41; eval("x + 1")"#).unwrap();
+    /// assert_eq!(value, CrValue::Number(42.into()));
+    /// let error = cara::eval(r#"eval("1 +")"#).unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::Syntax(_))));
+    /// ```
+    Syntax(ParseError),
     InvalidInit,
     BadAssign,
+    /// A builtin which modifies a list was given something other than a variable. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::{CrValue, Error};
+    /// use cara::CaraError;
+    ///
+    /// assert_eq!(cara::eval("len([1, 2, 3])").unwrap(), CrValue::Number(3.into()));
+    /// let error = cara::eval("append([1, 2], 3);").unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::NotAssignable)));
+    /// ```
+    NotAssignable,
+    /// A `break` or `continue` which isn't inside a loop of the same function. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::Error;
+    /// use cara::CaraError;
+    ///
+    /// let in_function = cara::eval("fn f() { break; } while true { f(); }");
+    /// assert!(matches!(in_function, Err(CaraError::Runtime(Error::NotInLoop))));
+    /// let top_level = cara::eval("continue");
+    /// assert!(matches!(top_level, Err(CaraError::Runtime(Error::NotInLoop))));
+    /// ```
     NotInLoop,
     RetValInVoidFunc,
     DerefInt,
     UseVoidValue,
     ArgMismatch,
+    /// A function called with more or fewer arguments than it has parameters. The
+    /// name is the one it was called by, or `<lambda>` when it wasn't called by name. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::Error;
+    /// use cara::CaraError;
+    ///
+    /// let error = cara::eval("fn add(a, b) { return a + b; } add(1)").unwrap_err();
+    /// assert!(matches!(
+    ///     error,
+    ///     CaraError::Runtime(Error::ArityMismatch { name, expected: 2, found: 1 }) if name == "add"
+    /// ));
+    /// let error = cara::eval("var add = fn(a, b) { return a + b; }; add(1, 2, 3)").unwrap_err();
+    /// assert_eq!(error.to_string(), "runtime error: add expected 2 arguments, found 3");
+    /// let error = cara::eval("map([1], fn(a, b) { return a; })").unwrap_err();
+    /// assert_eq!(error.to_string(), "runtime error: <lambda> expected 2 arguments, found 1");
+    /// ```
+    ArityMismatch {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    /// An operator applied to values it doesn't support. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::Error;
+    /// use cara::CaraError;
+    ///
+    /// let error = cara::eval("[1, 2] + 3").unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::NonIntCalc)));
+    /// assert_eq!(error.to_string(), "runtime error: unsupported operand types");
+    /// ```
     NonIntCalc,
+    NonBoolCondition,
+    /// A shift by a negative amount, or a left shift by an absurdly large one. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::{CrValue, Error};
+    /// use cara::CaraError;
+    ///
+    /// assert_eq!(cara::eval("1 << 10").unwrap(), CrValue::Number(1024.into()));
+    /// let error = cara::eval("1 << -1").unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::BadShift)));
+    /// let error = cara::eval("1 << 1000000000000").unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::BadShift)));
+    /// // Right shifts can't grow the number, so any amount works.
+    /// assert_eq!(cara::eval("1 >> 1000000000000").unwrap(), CrValue::Number(0.into()));
+    /// ```
+    BadShift,
     UnknownOperator,
+    StepLimitExceeded,
+    IntTooLarge,
+    ListTooLong,
+    UnhashableKey,
+    KeyNotFound,
+    /// A failed `assert` or `assert_eq`, with a message describing it. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::{CrValue, Error};
+    /// use cara::CaraError;
+    ///
+    /// assert_eq!(cara::eval("assert(1 < 2); assert_eq([1, 2], [1, 2])").unwrap(), CrValue::Void);
+    /// let error = cara::eval("assert_eq(2 * 3, 5)").unwrap_err();
+    /// assert_eq!(error.to_string(), "runtime error: assertion failed: 6 != 5");
+    /// let error = cara::eval("assert(1 > 2)").unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::AssertionFailed(_))));
+    /// ```
+    AssertionFailed(String),
+    /// A string given to `int` which isn't a decimal integer. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::Error;
+    /// use cara::CaraError;
+    ///
+    /// let error = cara::eval(r#"int("abc")"#).unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::ParseInt(_))));
+    /// ```
+    ParseInt(String),
+    /// `input` called on an interpreter without a reader. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::Error;
+    /// use cara::CaraError;
+    ///
+    /// let error = cara::eval("input()").unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::NoReader)));
+    /// ```
+    NoReader,
+    /// Assigning to several variables at once from a value which isn't a list of as
+    /// many elements. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::Error;
+    /// use cara::CaraError;
+    ///
+    /// let error = cara::eval("var a, b = 1, 2, 3;").unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::UnpackMismatch)));
+    /// let error = cara::eval("var a, b = 1;").unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::UnpackMismatch)));
+    /// ```
+    UnpackMismatch,
+    Timeout,
     Return(CrValue),
-    Break,
+    TailCall(Vec<CrValue>),
+    Break(Option<CrValue>),
     Continue,
 }
 
@@ -26,19 +225,47 @@ impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Self::DuplicatedDef => write!(f, "duplicated symbol definition"),
-            Self::SymbolNotFound(id) => write!(f, "symbol not found, id: {id}"),
+            Self::SymbolNotFound(name) => write!(f, "symbol not found: {name}"),
+            Self::NotCallable(name) => write!(f, "value is not callable: {name}"),
             Self::FailedToEval => write!(f, "failed to evaluate constant"),
             Self::InvalidArrayLen => write!(f, "invalid array length"),
+            Self::IndexOutOfRange => write!(f, "list index out of range"),
+            Self::EmptyList => write!(f, "list is empty"),
+            Self::ZeroStep => write!(f, "step of zero"),
+            Self::DivByZero => write!(f, "division by zero"),
+            Self::Syntax(error) => write!(f, "syntax error in eval: {error}"),
             Self::InvalidInit => write!(f, "invalid initializer"),
             Self::BadAssign => write!(f, "assigning to constant"),
+            Self::NotAssignable => write!(f, "only variables can be modified"),
             Self::NotInLoop => write!(f, "using break/continue outside of loop"),
             Self::RetValInVoidFunc => write!(f, "returning value in void fucntion"),
             Self::DerefInt => write!(f, "dereferencing an integer"),
             Self::UseVoidValue => write!(f, "using a void value"),
             Self::ArgMismatch => write!(f, "argument mismatch"),
-            Self::NonIntCalc => write!(f, "non-integer calculation"),
+            Self::ArityMismatch {
+                name,
+                expected,
+                found,
+            } => {
+                write!(f, "{name} expected {expected} arguments, found {found}")
+            }
+            Self::NonIntCalc => write!(f, "unsupported operand types"),
+            Self::NonBoolCondition => write!(f, "non-bool condition"),
+            Self::BadShift => write!(f, "negative or too large shift amount"),
             Self::UnknownOperator => write!(f, "unknown operator"),
-            _ => Ok(()),
+            Self::StepLimitExceeded => write!(f, "step limit exceeded"),
+            Self::IntTooLarge => write!(f, "integer result too large"),
+            Self::ListTooLong => write!(f, "list too long"),
+            Self::UnhashableKey => write!(f, "value can't be a map key"),
+            Self::KeyNotFound => write!(f, "key not found in map"),
+            Self::AssertionFailed(message) => write!(f, "assertion failed: {message}"),
+            Self::ParseInt(string) => write!(f, "invalid integer: {string:?}"),
+            Self::UnpackMismatch => write!(f, "wrong number of values to unpack"),
+            Self::NoReader => write!(f, "no input available"),
+            Self::Timeout => write!(f, "time limit exceeded"),
+            // These only escape when a script misuses `return`, `break` or `continue`.
+            Self::Return(_) | Self::TailCall(_) => write!(f, "returning outside of function"),
+            Self::Break(_) | Self::Continue => write!(f, "using break/continue outside of loop"),
         }
     }
 }
@@ -49,5 +276,8 @@ impl fmt::Debug for Error {
     }
 }
 
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
 /// Result type of IR generator.
 pub type Result<T> = core::result::Result<T, Error>;
@@ -1,115 +1,268 @@
-use alloc::{collections::BTreeMap, rc::Rc, vec::Vec};
+use alloc::{collections::BTreeMap, string::String, vec, vec::Vec};
+use core::mem;
 use core::ops::{Deref, DerefMut};
 
 use super::{
     result::{Error, Result},
     value::CrValue,
 };
-use crate::ast::AstNodes;
 
 #[derive(Debug, Clone)]
 pub enum Symbol {
     Const(usize, CrValue),
     Var(usize, CrValue),
-    Function(usize, Rc<Vec<usize>>, Rc<Vec<AstNodes>>),
 }
 
 impl Symbol {
     pub fn get_id(&self) -> &usize {
         match self {
-            Self::Const(id, _) | Self::Var(id, _) | Self::Function(id, _, _) => id,
+            Self::Const(id, _) | Self::Var(id, _) => id,
         }
     }
 
     pub const fn get_value(&self) -> Result<&CrValue> {
         match self {
             Self::Const(_, value) | Self::Var(_, value) => Ok(value),
-            Self::Function(_, _, _) => Err(Error::UseVoidValue),
         }
     }
 
+    /// Returns the value for changing in place, which constants don't allow.
     pub fn get_value_mut(&mut self) -> Result<&mut CrValue> {
         match self {
-            Self::Const(_, value) | Self::Var(_, value) => Ok(value),
-            Self::Function(_, _, _) => Err(Error::UseVoidValue),
+            Self::Const(_, _) => Err(Error::BadAssign),
+            Self::Var(_, value) => Ok(value),
+        }
+    }
+
+    pub fn to_callable(&self) -> Option<CrValue> {
+        match self {
+            Self::Const(_, value) | Self::Var(_, value) => {
+                value.is_callable().then(|| value.clone())
+            }
         }
     }
 
     pub fn assign(&mut self, value: CrValue) -> Result<()> {
         match self {
-            Self::Const(_, _) | Self::Function(_, _, _) => return Err(Error::BadAssign),
+            Self::Const(_, _) => return Err(Error::BadAssign),
             Self::Var(_, old_value) => *old_value = value,
         }
         Ok(())
     }
 }
 
+/// Symbols of a single scope, stored in slots indexed by their interned id. \
+/// Example
+/// ```rust
+/// use cara::backend::{CrValue, Error};
+/// use cara::CaraError;
+///
+/// // A name resolves to its innermost binding, and the outer one comes back after.
+/// let src = "var x = 1; var seen = []; if true { var x = 2; if true { var x = 3; push(seen, x); } push(seen, x); } push(seen, x); seen";
+/// assert_eq!(cara::eval(src).unwrap(), cara::eval("[3, 2, 1]").unwrap());
+///
+/// // A scope's slots are cleared when it ends, even when its table is reused.
+/// let error = cara::eval("if true { var y = 1; } y").unwrap_err();
+/// assert!(matches!(error, CaraError::Runtime(Error::SymbolNotFound(name)) if name == "y"));
+/// let src = "for i in (0, 2) { if i == 0 { var y = 5; } else { y; } }";
+/// let error = cara::eval(src).unwrap_err();
+/// assert!(matches!(error, CaraError::Runtime(Error::SymbolNotFound(name)) if name == "y"));
+/// ```
 pub struct SymbolTable {
-    symbols: BTreeMap<usize, Symbol>,
+    symbols: Vec<Option<Symbol>>,
 }
 
 impl SymbolTable {
-    pub fn new() -> Self {
+    pub const fn new() -> Self {
         Self {
-            symbols: BTreeMap::new(),
+            symbols: Vec::new(),
         }
     }
 
+    pub fn get(&self, id: usize) -> Option<&Symbol> {
+        self.symbols.get(id).and_then(Option::as_ref)
+    }
+
+    pub fn get_mut(&mut self, id: usize) -> Option<&mut Symbol> {
+        self.symbols.get_mut(id).and_then(Option::as_mut)
+    }
+
+    pub fn insert(&mut self, symbol: Symbol) {
+        let id = *symbol.get_id();
+        if id >= self.symbols.len() {
+            self.symbols.resize(id + 1, None);
+        }
+        self.symbols[id] = Some(symbol);
+    }
+
     pub fn clear(&mut self) {
         self.symbols.clear();
     }
-}
 
-pub struct SymbolTables(pub Vec<SymbolTable>);
-
-impl From<Vec<SymbolTable>> for SymbolTables {
-    fn from(value: Vec<SymbolTable>) -> Self {
-        Self(value)
+    pub fn iter(&self) -> impl Iterator<Item = &Symbol> {
+        self.symbols.iter().flatten()
     }
 }
 
+pub struct SymbolTables {
+    tables: Vec<SymbolTable>,
+    /// Index of the first local scope visible besides the global one. Scopes below it
+    /// belong to the callers of the running function and are hidden from it.
+    base: usize,
+    /// Popped tables kept around so pushing a scope doesn't allocate again.
+    spare: Vec<SymbolTable>,
+    string_table: Vec<String>,
+    /// Ids of the strings in the string table, for names only known at runtime.
+    ids: BTreeMap<String, usize>,
+}
+
 impl Deref for SymbolTables {
     type Target = [SymbolTable];
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.tables
     }
 }
 
 impl DerefMut for SymbolTables {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+        &mut self.tables
     }
 }
 
 impl SymbolTables {
+    pub fn new(string_table: Vec<String>) -> Self {
+        let ids = string_table
+            .iter()
+            .enumerate()
+            .map(|(id, name)| (name.clone(), id))
+            .collect();
+        Self {
+            tables: vec![SymbolTable::new()],
+            base: 0,
+            spare: Vec::new(),
+            string_table,
+            ids,
+        }
+    }
+
+    pub fn string_table(&self) -> &[String] {
+        &self.string_table
+    }
+
+    pub fn name(&self, id: usize) -> &str {
+        &self.string_table[id]
+    }
+
+    /// Returns the id of `name`, adding it to the string table if it's new.
+    pub fn intern(&mut self, name: &str) -> usize {
+        self.lookup(name).unwrap_or_else(|| {
+            let id = self.string_table.len();
+            self.string_table.push(name.into());
+            self.ids.insert(name.into(), id);
+            id
+        })
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<usize> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn not_found(&self, id: usize) -> Error {
+        Error::SymbolNotFound(self.string_table[id].clone())
+    }
+
+    pub fn push(&mut self) {
+        let table = self.spare.pop().unwrap_or_else(SymbolTable::new);
+        self.tables.push(table);
+    }
+
+    pub fn pop(&mut self) {
+        let mut table = self.tables.pop().unwrap();
+        table.clear();
+        self.spare.push(table);
+    }
+
+    /// Hides every local scope from lookups until [`SymbolTables::show_locals`] is
+    /// called with the returned value.
+    pub fn hide_locals(&mut self) -> usize {
+        mem::replace(&mut self.base, self.tables.len())
+    }
+
+    pub fn show_locals(&mut self, base: usize) {
+        self.base = base;
+    }
+
     #[allow(unused)]
     pub fn last(&self) -> &SymbolTable {
-        self.0.last().unwrap()
+        self.tables.last().unwrap()
     }
 
     pub fn last_mut(&mut self) -> &mut SymbolTable {
-        self.0.last_mut().unwrap()
+        self.tables.last_mut().unwrap()
+    }
+
+    pub fn insert_global(&mut self, symbol: Symbol) {
+        self.tables[0].insert(symbol);
     }
 
     pub fn insert_sym(&mut self, symbol: Symbol) {
-        self.last_mut().symbols.insert(*symbol.get_id(), symbol);
+        self.last_mut().insert(symbol);
+    }
+
+    /// Adds a symbol defined by `var` or `const` to the innermost scope. A constant
+    /// can't be defined again in the same scope, and neither can a variable be
+    /// turned into one.
+    pub fn define_sym(&mut self, symbol: Symbol) -> Result<()> {
+        let existing = self.last().get(*symbol.get_id());
+        match (existing, &symbol) {
+            (Some(Symbol::Const(..)), _) | (Some(_), Symbol::Const(..)) => {
+                Err(Error::DuplicatedDef)
+            }
+            _ => {
+                self.insert_sym(symbol);
+                Ok(())
+            }
+        }
     }
 
     pub fn clear_last(&mut self) {
         self.last_mut().clear();
     }
 
+    /// Copies the values of every local variable visible from the current scope,
+    /// with inner scopes shadowing outer ones.
+    pub fn capture_locals(&self) -> Vec<(usize, CrValue)> {
+        let mut captured = BTreeMap::new();
+        for table in &self.tables[self.base.max(1)..] {
+            for Symbol::Const(id, value) | Symbol::Var(id, value) in table.iter() {
+                captured.insert(*id, value.clone());
+            }
+        }
+        captured.into_iter().collect()
+    }
+
+    /// Returns the symbol `id` refers to from the current scope, if there is one.
+    #[inline]
+    fn find(&self, id: usize) -> Option<&Symbol> {
+        let (global, locals) = self.tables.split_first().unwrap();
+        locals[self.base.saturating_sub(1)..]
+            .iter()
+            .rev()
+            .find_map(|symt| symt.get(id))
+            .or_else(|| global.get(id))
+    }
+
+    pub fn contains(&self, id: usize) -> bool {
+        self.find(id).is_some()
+    }
+
     #[inline]
     fn get_var<F, R>(&self, id: usize, f: F) -> R
     where
         F: FnOnce(Result<&Symbol>) -> R,
     {
-        let sym = self
-            .iter()
-            .filter_map(|symt| symt.symbols.get(&id))
-            .next_back();
-        f(sym.ok_or(Error::SymbolNotFound(id)))
+        f(self.find(id).ok_or_else(|| self.not_found(id)))
     }
 
     #[inline]
@@ -117,11 +270,14 @@ impl SymbolTables {
     where
         F: FnOnce(Result<&'a mut Symbol>) -> R,
     {
-        let sym = self
+        let (global, locals) = self.tables.split_first_mut().unwrap();
+        let sym = locals[self.base.saturating_sub(1)..]
             .iter_mut()
-            .filter_map(|symt| symt.symbols.get_mut(&id))
-            .next_back();
-        f(sym.ok_or(Error::SymbolNotFound(id)))
+            .rev()
+            .find_map(|symt| symt.get_mut(id))
+            .or_else(|| global.get_mut(id));
+        let string_table = &self.string_table;
+        f(sym.ok_or_else(|| Error::SymbolNotFound(string_table[id].clone())))
     }
 
     #[inline]
@@ -137,18 +293,15 @@ impl SymbolTables {
     #[inline]
     pub fn symbol_crvalue_len(&self, id: usize) -> Result<usize> {
         self.get_var(id, |sym| {
-            sym.and_then(Symbol::get_value)
-                .and_then(CrValue::as_list)
-                .map(Vec::len)
+            sym.and_then(Symbol::get_value).and_then(CrValue::length)
         })
     }
 
     #[inline]
-    pub fn symbol_crvalue_list_item(&self, id: usize, index: usize) -> Result<CrValue> {
+    pub fn symbol_crvalue_item(&self, id: usize, index: &CrValue) -> Result<CrValue> {
         self.get_var(id, |sym| {
             sym.and_then(Symbol::get_value)
-                .and_then(CrValue::as_list)
-                .map(|list| list[index].clone())
+                .and_then(|value| value.item(index))
         })
     }
 
@@ -172,16 +325,52 @@ impl SymbolTables {
 
     #[inline]
     pub fn symbol_list_insert(&mut self, id: usize, index: usize, value: CrValue) -> Result<()> {
-        self.symbol_list_mut(id).map(|vec| vec.insert(index, value))
+        let vec = self.symbol_list_mut(id)?;
+        if index > vec.len() {
+            return Err(Error::IndexOutOfRange);
+        }
+        vec.insert(index, value);
+        Ok(())
+    }
+
+    /// Replaces the element reached by indexing into nested lists and maps along
+    /// `path`, or adds it if the last step is a map without that key.
+    pub fn symbol_modify_path(
+        &mut self,
+        id: usize,
+        path: &[CrValue],
+        value: CrValue,
+    ) -> Result<()> {
+        let (last, path) = path.split_last().ok_or(Error::BadAssign)?;
+        let mut target = self.get_var_mut(id, |sym| sym.and_then(Symbol::get_value_mut))?;
+        for index in path {
+            target = target.item_mut(index)?;
+        }
+        target.set_item(last, value)
+    }
+
+    /// Replaces the elements of a list variable, keeping its kind.
+    #[inline]
+    pub fn symbol_list_replace(&mut self, id: usize, list: Vec<CrValue>) -> Result<()> {
+        self.symbol_list_mut(id).map(|vec| *vec = list)
     }
 
     #[inline]
-    pub fn symbol_list_modify(&mut self, id: usize, index: usize, value: CrValue) -> Result<()> {
-        self.symbol_list_mut(id).map(|vec| vec[index] = value)
+    pub fn symbol_list_reverse(&mut self, id: usize) -> Result<()> {
+        self.symbol_list_mut(id).map(|vec| vec.reverse())
+    }
+
+    #[inline]
+    pub fn symbol_list_pop(&mut self, id: usize) -> Result<CrValue> {
+        self.symbol_list_mut(id)?.pop().ok_or(Error::EmptyList)
     }
 
     #[inline]
     pub fn symbol_list_remove(&mut self, id: usize, index: usize) -> Result<CrValue> {
-        self.symbol_list_mut(id).map(|vec| vec.remove(index))
+        let vec = self.symbol_list_mut(id)?;
+        if index >= vec.len() {
+            return Err(Error::IndexOutOfRange);
+        }
+        Ok(vec.remove(index))
     }
 }
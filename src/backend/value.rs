@@ -1,35 +1,418 @@
-use alloc::{rc::Rc, string::String, vec::Vec};
+use alloc::collections::BTreeMap;
+use alloc::{rc::Rc, string::String, string::ToString, vec::Vec};
+use core::cell::RefCell;
+use core::cmp::Ordering;
 use core::fmt::{self, Display};
 use dashu_int::IBig;
 
 use super::result::{Error, Result};
 use crate::ast::AstNodes;
 
+/// Argument lists a memoized function has been called with, along with their results.
+pub type MemoCache = Rc<RefCell<BTreeMap<Vec<MemoKey>, CrValue>>>;
+
+/// An argument of a memoized call as a cache key. Unlike [`CrValue`]'s equality it
+/// tells integers and floats apart, so `f(1)` and `f(1.0)` are cached separately.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MemoKey {
+    Number(IBig),
+    /// The bits of the float, which also tell `0.0` and `-0.0` apart.
+    Float(u64),
+    Bool(bool),
+    Str(String),
+    Char(char),
+    List(Vec<MemoKey>),
+    Frozen(Vec<MemoKey>),
+    Map(Vec<(MapKey, MemoKey)>),
+    Void,
+}
+
+impl MemoKey {
+    /// Makes the key of a value, or `None` if the value holds a function, whose calls
+    /// aren't cached.
+    pub fn new(value: &CrValue) -> Option<Self> {
+        let list = |values: &[CrValue]| values.iter().map(Self::new).collect::<Option<Vec<_>>>();
+        Some(match value {
+            CrValue::Number(number) => Self::Number(number.clone()),
+            CrValue::Float(value) => Self::Float(value.to_bits()),
+            CrValue::Bool(value) => Self::Bool(*value),
+            CrValue::Str(string) => Self::Str(string.clone()),
+            CrValue::Char(ch) => Self::Char(*ch),
+            CrValue::List(values) => Self::List(list(values)?),
+            CrValue::Frozen(values) => Self::Frozen(list(values)?),
+            CrValue::Map(map) => Self::Map(
+                map.iter()
+                    .map(|(key, value)| Some((key.clone(), Self::new(value)?)))
+                    .collect::<Option<_>>()?,
+            ),
+            CrValue::Void => Self::Void,
+            CrValue::Function(..) | CrValue::Memoized(..) => return None,
+        })
+    }
+}
+
+/// A value which can be a key of a map. Only numbers, bools, strings and characters
+/// can, since they are ordered and can't change.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MapKey {
+    Number(IBig),
+    Bool(bool),
+    Str(String),
+    Char(char),
+}
+
+impl TryFrom<CrValue> for MapKey {
+    type Error = Error;
+
+    fn try_from(value: CrValue) -> Result<Self> {
+        match value {
+            CrValue::Number(number) => Ok(Self::Number(number)),
+            CrValue::Bool(value) => Ok(Self::Bool(value)),
+            CrValue::Str(string) => Ok(Self::Str(string)),
+            CrValue::Char(ch) => Ok(Self::Char(ch)),
+            _ => Err(Error::UnhashableKey),
+        }
+    }
+}
+
+impl From<MapKey> for CrValue {
+    fn from(key: MapKey) -> Self {
+        match key {
+            MapKey::Number(number) => Self::Number(number),
+            MapKey::Bool(value) => Self::Bool(value),
+            MapKey::Str(string) => Self::Str(string),
+            MapKey::Char(ch) => Self::Char(ch),
+        }
+    }
+}
+
+/// Local variables a function captured where it was defined, as copies of their values.
+pub type Captures = Rc<Vec<(usize, CrValue)>>;
+
 #[derive(Debug, Clone)]
 pub enum CrValue {
     Number(IBig),
-    Function(Rc<Vec<String>>, Rc<Vec<AstNodes>>),
+    /// A floating-point number. Arithmetic with a float and an integer converts the
+    /// integer to a float, but integers alone keep integer division. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::CrValue;
+    ///
+    /// assert_eq!(cara::eval("1.5 + 2").unwrap(), CrValue::Float(3.5));
+    /// assert_eq!(cara::eval("3.0 / 2").unwrap(), CrValue::Float(1.5));
+    /// assert_eq!(cara::eval("3 / 2").unwrap(), CrValue::Number(1.into()));
+    /// assert_eq!(cara::eval("1.0e3 == 1000").unwrap(), CrValue::Bool(true));
+    /// assert_eq!(cara::eval("2.5E-1").unwrap(), CrValue::Float(0.25));
+    /// assert_eq!(cara::eval("-2.0 ** 3").unwrap(), CrValue::Float(-8.0));
+    /// assert_eq!(cara::eval("2.0").unwrap().to_string(), "2.0");
+    /// ```
+    Float(f64),
+    Bool(bool),
+    Str(String),
+    /// A single character, written `'a'`, which compares by its code point. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::CrValue;
+    ///
+    /// assert_eq!(cara::eval("'a' < 'b' && 'c' == 'c'").unwrap(), CrValue::Bool(true));
+    /// assert_eq!(cara::eval("chr(ord('a') + 1)").unwrap(), CrValue::Char('b'));
+    /// ```
+    Char(char),
+    /// A function, which can be passed around like any other value. It holds the
+    /// parameters, the body and the variables captured where it was defined. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::CrValue;
+    ///
+    /// let src = "
+    ///     fn make_adder(n) { fn add(x) { return x + n; } return add; }
+    ///     var add_three = make_adder(3);
+    ///     add_three(4)
+    /// ";
+    /// assert_eq!(cara::eval(src).unwrap(), CrValue::Number(7.into()));
+    ///
+    /// let src = "fn apply(f, x) { return f(x); } fn inc(n) { return n + 1; } apply(inc, 4)";
+    /// assert_eq!(cara::eval(src).unwrap(), CrValue::Number(5.into()));
+    ///
+    /// // Each call starts from the captured values, which the body may change.
+    /// let src = "
+    ///     fn mk() { var c = 0; fn inc() { c = c + 1; return c; } return inc; }
+    ///     fn mk_list() { var xs = [1]; fn add(x) { push(xs, x); return xs; } return add; }
+    ///     var inc = mk();
+    ///     [mk()(), inc(), inc(), mk_list()(2)]
+    /// ";
+    /// assert_eq!(cara::eval(src).unwrap(), cara::eval("[1, 1, 1, [1, 2]]").unwrap());
+    /// ```
+    Function(Rc<Vec<usize>>, Rc<Vec<AstNodes>>, Captures),
+    /// A function wrapped by `memoize`, with the results of the calls made so far. \
+    /// Example
+    /// ```rust
+    /// let src = "
+    ///     var calls = 0;
+    ///     const double = memoize(fn(x) { calls = calls + 1; return x * 2; });
+    ///     [double(2), double(2), double(3), double(2), calls]
+    /// ";
+    /// assert_eq!(cara::eval(src).unwrap(), cara::eval("[4, 4, 6, 4, 2]").unwrap());
+    ///
+    /// // Arguments which compare equal but have different types are cached apart.
+    /// let src = "fn t(x) { return typeof(x); } var m = memoize(t); [m(1), m(1.0), m(1)]";
+    /// let expected = r#"["number", "float", "number"]"#;
+    /// assert_eq!(cara::eval(src).unwrap(), cara::eval(expected).unwrap());
+    /// ```
+    Memoized(Rc<CrValue>, MemoCache),
     List(Vec<CrValue>),
+    /// An immutable snapshot of a list, made by `freeze`. `thaw` gives back a list
+    /// which can be changed again. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::Error;
+    /// use cara::CaraError;
+    ///
+    /// for src in ["push(f, 4);", "f[0] = 0;", "sort(f);", "reverse(f);", "pop(f);"] {
+    ///     let src = format!("var f = freeze([3, 1, 2]); {src}");
+    ///     let error = cara::eval(&src).unwrap_err();
+    ///     assert!(matches!(error, CaraError::Runtime(Error::BadAssign)), "{src}");
+    /// }
+    ///
+    /// let src = "var f = freeze([3, 1, 2]); var t = thaw(f); push(t, 4); t[0] = 0; sort(t); [t, f]";
+    /// assert_eq!(cara::eval(src).unwrap(), cara::eval("[[0, 1, 2, 4], [3, 1, 2]]").unwrap());
+    /// ```
+    Frozen(Rc<Vec<CrValue>>),
+    /// Values looked up by keys, written `{"a": 1, "b": 2}`. Indexing a map with a
+    /// key it doesn't have is an error. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::{CrValue, Error};
+    /// use cara::CaraError;
+    ///
+    /// let src = r#"var m = {"a": 1, "b": 2}; m["b"] = 3; m["c"] = 4; [m["a"], m["b"], m["c"]]"#;
+    /// assert_eq!(cara::eval(src).unwrap(), cara::eval("[1, 3, 4]").unwrap());
+    /// let src = r#"var m = {1: [0, 0]}; m[1][0] = 5; m == {1: [5, 0]}"#;
+    /// assert_eq!(cara::eval(src).unwrap(), CrValue::Bool(true));
+    ///
+    /// let error = cara::eval(r#"var m = {"a": 1}; m["z"]"#).unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::KeyNotFound)));
+    /// let error = cara::eval("var m = {[1]: 1};").unwrap_err();
+    /// assert!(matches!(error, CaraError::Runtime(Error::UnhashableKey)));
+    /// ```
+    Map(BTreeMap<MapKey, CrValue>),
     Void,
 }
 
 impl Display for CrValue {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::Number(number) => write!(f, "{number}"),
-            Self::Function(_, _) => write!(f, "function"),
-            Self::Void => write!(f, "void"),
-            Self::List(data) => {
+        self.display(None).fmt(f)
+    }
+}
+
+/// How many lists deep values are displayed before the rest is shown as `...`.
+const MAX_DISPLAY_DEPTH: usize = 64;
+
+/// Formats a value like its [`Display`] implementation, but shortens numbers with
+/// more than `max_digits` digits. Made by [`CrValue::display`]. \
+/// Lists nested deeper than 64 levels are cut off, so displaying them can't overflow
+/// the stack.
+/// ```rust
+/// let src = "var l = [1]; for i in (0, 100) { l = [l]; } l";
+/// let text = cara::eval(src).unwrap().to_string();
+/// assert!(text.starts_with(&"[".repeat(64)));
+/// assert!(text.contains("[...]"));
+/// assert!(!text.contains('1'));
+///
+/// let text = cara::eval("var l = [1]; for i in (0, 3) { l = [l, l]; } l").unwrap();
+/// assert_eq!(text.to_string(), "[[[[1,],[1,],],[[1,],[1,],],],[[[1,],[1,],],[[1,],[1,],],],]");
+/// ```
+pub struct DisplayValue<'a> {
+    value: &'a CrValue,
+    max_digits: Option<usize>,
+    /// How many lists contain the value.
+    depth: usize,
+}
+
+impl Display for DisplayValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.value {
+            CrValue::Number(number) => match self.max_digits {
+                Some(max_digits) => {
+                    let string = number.to_string();
+                    let (sign, digits) = string.split_at(usize::from(string.starts_with('-')));
+                    if digits.len() <= max_digits {
+                        return write!(f, "{string}");
+                    }
+                    let head = &digits[..max_digits.div_ceil(2)];
+                    let tail = &digits[digits.len() - max_digits / 2..];
+                    write!(f, "{sign}{head}...{tail} ({} digits)", digits.len())
+                }
+                None => write!(f, "{number}"),
+            },
+            CrValue::List(_) | CrValue::Frozen(_) if self.depth >= MAX_DISPLAY_DEPTH => {
+                write!(f, "[...]")
+            }
+            CrValue::List(_) | CrValue::Frozen(_) => {
                 write!(f, "[")?;
-                data.iter().try_for_each(|item| write!(f, "{item},"))?;
-                write!(f, "]")?;
-                Ok(())
+                self.value.as_list().unwrap().iter().try_for_each(|item| {
+                    let item = DisplayValue {
+                        value: item,
+                        max_digits: self.max_digits,
+                        depth: self.depth + 1,
+                    };
+                    write!(f, "{item},")
+                })?;
+                write!(f, "]")
             }
+            CrValue::Float(value) => write!(f, "{value:?}"),
+            CrValue::Map(_) if self.depth >= MAX_DISPLAY_DEPTH => write!(f, "{{...}}"),
+            CrValue::Map(map) => {
+                write!(f, "{{")?;
+                map.iter().try_for_each(|(key, value)| {
+                    let key = CrValue::from(key.clone());
+                    let value = DisplayValue {
+                        value,
+                        max_digits: self.max_digits,
+                        depth: self.depth + 1,
+                    };
+                    write!(f, "{}:{value},", key.display(self.max_digits))
+                })?;
+                write!(f, "}}")
+            }
+            CrValue::Bool(value) => write!(f, "{value}"),
+            CrValue::Str(string) => write!(f, "{string}"),
+            CrValue::Char(ch) => write!(f, "{ch}"),
+            CrValue::Function(..) | CrValue::Memoized(_, _) => write!(f, "function"),
+            CrValue::Void => write!(f, "void"),
         }
     }
 }
 
 impl CrValue {
+    /// Returns an object which displays the value with numbers shortened to about
+    /// `max_digits` digits, or in full if it's `None`. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::CrValue;
+    ///
+    /// let number = CrValue::Number("12345678901234567890".parse().unwrap());
+    /// assert_eq!(number.display(Some(8)).to_string(), "1234...7890 (20 digits)");
+    /// assert_eq!(number.display(None).to_string(), "12345678901234567890");
+    /// ```
+    #[must_use]
+    pub const fn display(&self, max_digits: Option<usize>) -> DisplayValue<'_> {
+        DisplayValue {
+            value: self,
+            max_digits,
+            depth: 0,
+        }
+    }
+}
+
+/// Values of different kinds are never equal, except integers and floats with the
+/// same value, and lists compare element by element. \
+/// Example
+/// ```rust
+/// use cara::backend::{CrValue, Error};
+/// use cara::CaraError;
+///
+/// assert_eq!(cara::eval("[1, [2, 3]] == [1, [2, 3]]").unwrap(), CrValue::Bool(true));
+/// assert_eq!(cara::eval("[1, 2] != [2, 1]").unwrap(), CrValue::Bool(true));
+/// assert_eq!(cara::eval("[1] == 1").unwrap(), CrValue::Bool(false));
+/// assert!(matches!(cara::eval("[1] < [2]"), Err(CaraError::Runtime(Error::NonIntCalc))));
+/// assert_eq!(cara::eval("[1 == 1.0, 1.0 != 1, [1] == [1.0]]").unwrap(), cara::eval("[true, false, true]").unwrap());
+///
+/// // Every kind of value equals itself and differs from all the others, without errors.
+/// let src = r#"
+///     fn nothing() { }
+///     var values = [1, 2.5, true, "a", 'a', [1], {"a": 1}, fn() { return 1; }, nothing()];
+///     var wrong = [];
+///     for i in (0, len(values)) {
+///         for j in (0, len(values)) {
+///             if (values[i] == values[j]) != (i == j) || (values[i] != values[j]) == (i == j) {
+///                 push(wrong, [i, j]);
+///             }
+///         }
+///     }
+///     wrong
+/// "#;
+/// assert_eq!(cara::eval(src).unwrap(), cara::eval("[]").unwrap());
+/// ```
+impl PartialEq for CrValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Number(left), Self::Number(right)) => left == right,
+            (Self::Float(_), Self::Number(_) | Self::Float(_))
+            | (Self::Number(_), Self::Float(_)) => self.as_float() == other.as_float(),
+            (Self::Bool(left), Self::Bool(right)) => left == right,
+            (Self::Str(left), Self::Str(right)) => left == right,
+            (Self::Char(left), Self::Char(right)) => left == right,
+            (Self::List(_) | Self::Frozen(_), Self::List(_) | Self::Frozen(_)) => {
+                self.as_list().unwrap() == other.as_list().unwrap()
+            }
+            (Self::Map(left), Self::Map(right)) => left == right,
+            (Self::Function(_, left, _), Self::Function(_, right, _)) => Rc::ptr_eq(left, right),
+            (Self::Memoized(_, left), Self::Memoized(_, right)) => Rc::ptr_eq(left, right),
+            (Self::Void, Self::Void) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Numbers, strings, characters and bools are ordered among their own kind, with
+/// integers and floats counting as one kind, and
+/// lists are ordered lexicographically. Other values can't be ordered. \
+/// Example
+/// ```rust
+/// use cara::backend::CrValue;
+///
+/// let src = r#"[cmp(1, 2), cmp("b", "a"), cmp([1, 2], [1, 2]), cmp([1], [1, 0])]"#;
+/// let expected = [-1, 1, 0, -1].map(|n| CrValue::Number(n.into()));
+/// assert_eq!(cara::eval(src).unwrap(), CrValue::List(expected.to_vec()));
+/// assert!(cara::eval(r#"cmp(1, "a")"#).is_err());
+/// ```
+impl PartialOrd for CrValue {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Self::Number(left), Self::Number(right)) => left.partial_cmp(right),
+            (Self::Float(_), Self::Number(_) | Self::Float(_))
+            | (Self::Number(_), Self::Float(_)) => self.as_float()?.partial_cmp(&other.as_float()?),
+            (Self::Bool(left), Self::Bool(right)) => left.partial_cmp(right),
+            (Self::Str(left), Self::Str(right)) => left.partial_cmp(right),
+            (Self::Char(left), Self::Char(right)) => left.partial_cmp(right),
+            (Self::List(_) | Self::Frozen(_), Self::List(_) | Self::Frozen(_)) => self
+                .as_list()
+                .unwrap()
+                .partial_cmp(other.as_list().unwrap()),
+            _ => None,
+        }
+    }
+}
+
+impl CrValue {
+    /// Returns the name of the value's type, which the `typeof` builtin gives to
+    /// scripts. \
+    /// Example
+    /// ```rust
+    /// let src = r#"fn f() {} [typeof(1), typeof([1]), typeof(f), typeof({}), typeof("a")]"#;
+    /// let expected = r#"["number", "list", "function", "map", "string"]"#;
+    /// assert_eq!(cara::eval(src).unwrap(), cara::eval(expected).unwrap());
+    /// ```
+    #[must_use]
+    pub const fn type_name(&self) -> &'static str {
+        match self {
+            Self::Number(_) => "number",
+            Self::Float(_) => "float",
+            Self::Bool(_) => "bool",
+            Self::Str(_) => "string",
+            Self::Char(_) => "char",
+            Self::Function(..) | Self::Memoized(..) => "function",
+            Self::List(_) | Self::Frozen(_) => "list",
+            Self::Map(_) => "map",
+            Self::Void => "void",
+        }
+    }
+
+    pub const fn is_callable(&self) -> bool {
+        matches!(self, Self::Function(..) | Self::Memoized(_, _))
+    }
+
     pub fn as_int(&self) -> Result<&IBig> {
         match self {
             Self::Number(num) => Ok(num),
@@ -37,9 +420,73 @@ impl CrValue {
         }
     }
 
+    /// Returns the value of a float, or of an integer converted to the nearest float.
+    #[must_use]
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Self::Number(num) => Some(num.to_f64().value()),
+            Self::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Evaluates the value as a condition.
+    /// Bools are taken as they are and numbers are true when they are not zero,
+    /// so `-1` is true as well. Any other value is not a valid condition.
+    pub fn is_truthy(&self) -> Result<bool> {
+        match self {
+            Self::Bool(value) => Ok(*value),
+            Self::Number(num) => Ok(*num != IBig::ZERO),
+            Self::Float(value) => Ok(*value != 0.0),
+            _ => Err(Error::NonBoolCondition),
+        }
+    }
+
+    /// Returns a copy of the value which shares no list with it, so it stays the same
+    /// whatever happens to the original. Functions are still shared, which keeps them
+    /// equal to the original. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::CrValue;
+    ///
+    /// let mut original = cara::eval("[1, freeze([2, 3])]").unwrap();
+    /// let copy = original.deep_clone();
+    /// assert_eq!(copy, original);
+    ///
+    /// original.as_list_mut().unwrap()[0] = CrValue::Number(4.into());
+    /// assert_ne!(copy, original);
+    /// assert_eq!(copy, cara::eval("[1, [2, 3]]").unwrap());
+    /// ```
+    #[must_use]
+    pub fn deep_clone(&self) -> Self {
+        match self {
+            Self::List(list) => Self::List(list.iter().map(Self::deep_clone).collect()),
+            Self::Frozen(list) => {
+                Self::Frozen(Rc::new(list.iter().map(Self::deep_clone).collect()))
+            }
+            Self::Map(map) => Self::Map(
+                map.iter()
+                    .map(|(key, value)| (key.clone(), value.deep_clone()))
+                    .collect(),
+            ),
+            value => value.clone(),
+        }
+    }
+
+    /// Returns the number of characters of a string, elements of a list or entries of
+    /// a map.
+    pub fn length(&self) -> Result<usize> {
+        match self {
+            Self::Str(string) => Ok(string.chars().count()),
+            Self::Map(map) => Ok(map.len()),
+            _ => self.as_list().map(Vec::len),
+        }
+    }
+
     pub fn as_list(&self) -> Result<&Vec<Self>> {
         match self {
             Self::List(list) => Ok(list),
+            Self::Frozen(list) => Ok(list),
             _ => Err(Error::UseVoidValue),
         }
     }
@@ -47,7 +494,54 @@ impl CrValue {
     pub fn as_list_mut(&mut self) -> Result<&mut Vec<Self>> {
         match self {
             Self::List(list) => Ok(list),
+            Self::Frozen(_) => Err(Error::BadAssign),
             _ => Err(Error::UseVoidValue),
         }
     }
+
+    /// Returns the element of a list at `index`, or the value of a map at the key
+    /// `index`.
+    pub fn item(&self, index: &Self) -> Result<Self> {
+        match self {
+            Self::Map(map) => map
+                .get(&MapKey::try_from(index.clone())?)
+                .cloned()
+                .ok_or(Error::KeyNotFound),
+            _ => self
+                .as_list()?
+                .get(list_index(index.as_int()?)?)
+                .cloned()
+                .ok_or(Error::IndexOutOfRange),
+        }
+    }
+
+    /// Returns the element of a list or the value of a map at `index` to change it.
+    pub fn item_mut(&mut self, index: &Self) -> Result<&mut Self> {
+        match self {
+            Self::Map(map) => map
+                .get_mut(&MapKey::try_from(index.clone())?)
+                .ok_or(Error::KeyNotFound),
+            _ => self
+                .as_list_mut()?
+                .get_mut(list_index(index.as_int()?)?)
+                .ok_or(Error::IndexOutOfRange),
+        }
+    }
+
+    /// Replaces the element of a list at `index`, or sets the value of a map at the
+    /// key `index`, adding it if the map doesn't have it.
+    pub fn set_item(&mut self, index: &Self, value: Self) -> Result<()> {
+        match self {
+            Self::Map(map) => {
+                map.insert(MapKey::try_from(index.clone())?, value);
+            }
+            _ => *self.item_mut(index)? = value,
+        }
+        Ok(())
+    }
+}
+
+/// Converts a number used as a list index, which can't be negative.
+pub(super) fn list_index(number: &IBig) -> Result<usize> {
+    usize::try_from(number).map_err(|_| Error::IndexOutOfRange)
 }
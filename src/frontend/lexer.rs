@@ -1,8 +1,11 @@
 use alloc::collections::BTreeMap;
 use alloc::string::String;
 use alloc::vec::Vec;
+use core::fmt;
 use dashu_int::IBig;
 
+use super::result::LexError;
+use super::Span;
 use crate::ast::Op;
 
 /// This enum defines all the token types with their values
@@ -14,12 +17,16 @@ pub enum KeywordTypes {
     Fn,
     Return,
     If,
+    Elif,
     Else,
     For,
     In,
     While,
+    Loop,
     Break,
     Continue,
+    True,
+    False,
 }
 
 impl KeywordTypes {
@@ -31,21 +38,49 @@ impl KeywordTypes {
             "fn" => Some(Self::Fn),
             "return" => Some(Self::Return),
             "if" => Some(Self::If),
+            "elif" => Some(Self::Elif),
             "else" => Some(Self::Else),
             "for" => Some(Self::For),
             "in" => Some(Self::In),
             "while" => Some(Self::While),
+            "loop" => Some(Self::Loop),
             "break" => Some(Self::Break),
             "continue" => Some(Self::Continue),
+            "true" => Some(Self::True),
+            "false" => Some(Self::False),
             _ => None,
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+impl fmt::Display for KeywordTypes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Var => "var",
+            Self::Const => "const",
+            Self::Fn => "fn",
+            Self::Return => "return",
+            Self::If => "if",
+            Self::Elif => "elif",
+            Self::Else => "else",
+            Self::For => "for",
+            Self::In => "in",
+            Self::While => "while",
+            Self::Loop => "loop",
+            Self::Break => "break",
+            Self::Continue => "continue",
+            Self::True => "true",
+            Self::False => "false",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     /// Numbers, such as 0,1,2,1234,114514 and so on.
     Number(IBig),
+    /// Numbers with a fraction or an exponent, such as 3.14 and 1.0e3.
+    Float(f64),
     /// Operators, +,-,*,/,......
     Operator(Op),
     /// `Left paren`, (
@@ -54,6 +89,10 @@ pub enum Token {
     RParen,
     /// `Identifiers`
     Id(usize),
+    /// `String literals`, "hello", interned like identifiers
+    Str(usize),
+    /// `Character literals`, 'a'
+    Char(char),
     /// `Keywords`
     Keyword(KeywordTypes),
     /// `Assign`, =
@@ -70,6 +109,37 @@ pub enum Token {
     LBracket,
     /// `RBracket`, }
     RBracket,
+    /// `Question`, ?
+    Question,
+    /// `Colon`, :
+    Colon,
+}
+
+/// Shows the token as it appears in the source, except for identifiers and strings,
+/// which only have interned ids.
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Number(number) => write!(f, "{number}"),
+            Self::Float(value) => write!(f, "{value:?}"),
+            Self::Operator(op) => write!(f, "`{op}`"),
+            Self::LParen => f.write_str("`(`"),
+            Self::RParen => f.write_str("`)`"),
+            Self::Id(_) => f.write_str("identifier"),
+            Self::Str(_) => f.write_str("string"),
+            Self::Char(ch) => write!(f, "{ch:?}"),
+            Self::Keyword(keyword) => write!(f, "`{keyword}`"),
+            Self::Assign => f.write_str("`=`"),
+            Self::Semi => f.write_str("`;`"),
+            Self::LBrace => f.write_str("`{`"),
+            Self::RBrace => f.write_str("`}`"),
+            Self::Comma => f.write_str("`,`"),
+            Self::LBracket => f.write_str("`[`"),
+            Self::RBracket => f.write_str("`]`"),
+            Self::Question => f.write_str("`?`"),
+            Self::Colon => f.write_str("`:`"),
+        }
+    }
 }
 
 impl Token {
@@ -91,10 +161,48 @@ impl Token {
     }
 }
 
-/// A simple and stupid Lexer
+/// A simple and stupid Lexer \
+/// Example
+/// ```rust
+/// use cara::ast::Op;
+/// use cara::frontend::{KeywordTypes as K, Lexer, Token as T};
+///
+/// let src = "var total = [0;3];\nfor i in (0, 3) {\n  total[i] = i * 2 + 1 << 2 >= 5 != 0 && i <= 7;\n}";
+/// let mut lexer = Lexer::new(src.into());
+/// let mut tokens = Vec::new();
+/// while let Some(token) = lexer.next_token().unwrap() {
+///     tokens.push(token);
+/// }
+/// let op = T::Operator;
+/// assert_eq!(tokens, [
+///     T::Keyword(K::Var), T::Id(0), T::Assign, T::LBracket, T::Number(0.into()), T::Semi,
+///     T::Number(3.into()), T::RBracket, T::Semi,
+///     T::Keyword(K::For), T::Id(1), T::Keyword(K::In), T::LParen, T::Number(0.into()),
+///     T::Comma, T::Number(3.into()), T::RParen, T::LBrace,
+///     T::Id(0), T::LBracket, T::Id(1), T::RBracket, T::Assign, T::Id(1), op(Op::Mul),
+///     T::Number(2.into()), op(Op::Add), T::Number(1.into()), op(Op::LShift),
+///     T::Number(2.into()), op(Op::Ge), T::Number(5.into()), op(Op::Ne), T::Number(0.into()),
+///     op(Op::And), T::Id(1), op(Op::Le), T::Number(7.into()), T::Semi,
+///     T::RBrace,
+/// ]);
+/// assert_eq!(lexer.string_table(), ["total", "i"]);
+///
+/// // Long sources are lexed to the end.
+/// let src = "x = x + 1;\n".repeat(10_000);
+/// assert_eq!(src.len(), 110_000);
+/// let mut lexer = Lexer::new(src);
+/// let mut count = 0;
+/// while lexer.next_token().unwrap().is_some() {
+///     count += 1;
+/// }
+/// assert_eq!(count, 60_000);
+/// ```
 pub struct Lexer {
-    input: String,
+    input: Vec<char>,
+    /// Offsets of the first character of every line.
+    line_starts: Vec<usize>,
     position: usize,
+    token_start: usize,
     strings: BTreeMap<String, usize>,
     string_table: Vec<String>,
     next_id: usize,
@@ -108,10 +216,16 @@ impl Lexer {
     /// let lexer = Lexer::new("1+2*3".into());
     /// ```
     #[must_use]
-    pub const fn new(input: String) -> Self {
+    pub fn new(input: String) -> Self {
+        let input: Vec<char> = input.chars().collect();
+        let line_starts = (0..=input.len())
+            .filter(|&offset| offset == 0 || input[offset - 1] == '\n')
+            .collect();
         Self {
             input,
+            line_starts,
             position: 0,
+            token_start: 0,
             strings: BTreeMap::new(),
             string_table: Vec::new(),
             next_id: 0,
@@ -119,23 +233,120 @@ impl Lexer {
     }
 
     fn advance(&mut self) -> Option<char> {
-        if self.position >= self.input.len() {
-            return None;
-        }
-        let c = self.input.chars().nth(self.position);
+        let c = self.input.get(self.position).copied()?;
         self.position += 1;
-        c
+        Some(c)
     }
 
     #[must_use]
     pub fn current_char(&self) -> char {
-        self.input.chars().nth(self.position).unwrap_or('\0')
+        self.char_at(0)
+    }
+
+    /// Returns the character `offset` places after the current one, or `'\0'` past the end.
+    fn char_at(&self, offset: usize) -> char {
+        self.input
+            .get(self.position + offset)
+            .copied()
+            .unwrap_or('\0')
+    }
+
+    /// Appends the decimal digits at the current position to `num`.
+    fn read_digits(&mut self, num: &mut String) {
+        while self.current_char().is_ascii_digit() {
+            num.extend(self.advance());
+        }
+    }
+
+    /// Returns the offset of the first character of the last token read.
+    #[must_use]
+    pub const fn token_start(&self) -> usize {
+        self.token_start
+    }
+
+    /// Returns the line and column of the character at `offset`. \
+    /// Example
+    /// ```rust
+    /// use cara::frontend::{Lexer, Span};
+    ///
+    /// let lexer = Lexer::new("var a = 1;\nvar b = 2;".into());
+    /// assert_eq!(lexer.span_at(4), Span { line: 1, col: 5 });
+    /// assert_eq!(lexer.span_at(15), Span { line: 2, col: 5 });
+    /// ```
+    #[must_use]
+    pub fn span_at(&self, offset: usize) -> Span {
+        let line = self.line_starts.partition_point(|start| *start <= offset);
+        Span {
+            line,
+            col: offset - self.line_starts[line - 1] + 1,
+        }
     }
 
     pub fn string_table(&self) -> Vec<String> {
         self.string_table.clone()
     }
 
+    fn intern(&mut self, string: String) -> usize {
+        if let Some(n) = self.strings.get(&string) {
+            *n
+        } else {
+            let n = self.next_id;
+            self.string_table.push(string.clone());
+            self.strings.insert(string, n);
+            self.next_id += 1;
+            n
+        }
+    }
+
+    fn read_string(&mut self) -> Result<String, LexError> {
+        let mut string = String::new();
+        loop {
+            match self.advance() {
+                Some('"') => return Ok(string),
+                Some('\\') => match self.read_escape() {
+                    Some(ch) => string.push(ch?),
+                    None => {
+                        return Err(LexError::UnterminatedString(self.span_at(self.token_start)))
+                    }
+                },
+                Some(ch) => string.push(ch),
+                None => return Err(LexError::UnterminatedString(self.span_at(self.token_start))),
+            }
+        }
+    }
+
+    /// Reads the character after a backslash and returns the one it stands for,
+    /// or `None` at the end of the input.
+    fn read_escape(&mut self) -> Option<Result<char, LexError>> {
+        let ch = match self.advance()? {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '\\' => '\\',
+            '"' => '"',
+            '\'' => '\'',
+            ch => {
+                return Some(Err(LexError::UnknownEscape(
+                    ch,
+                    self.span_at(self.position - 2),
+                )))
+            }
+        };
+        Some(Ok(ch))
+    }
+
+    fn read_char(&mut self) -> Result<char, LexError> {
+        let ch = match self.advance() {
+            Some('\\') => self.read_escape(),
+            Some('\'') | None => None,
+            ch => ch.map(Ok),
+        };
+        match (ch, self.advance()) {
+            (Some(ch), Some('\'')) => ch,
+            _ => Err(LexError::InvalidChar(self.span_at(self.token_start))),
+        }
+    }
+
     /// Let the lexer parse a token and return it. \
     /// Example
     /// ```rust
@@ -148,81 +359,95 @@ impl Lexer {
     /// // Output: Number(1) Operator('+') Number(2) Operator('*') Number(3)
     /// ```
     pub fn get_token(&mut self) -> Option<Token> {
+        self.next_token().unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Reads the next token like [`Lexer::get_token`], but returns an error on
+    /// malformed input instead of panicking. \
+    /// Example
+    /// ```rust
+    /// use cara::frontend::{LexError, Lexer, Span, Token};
+    ///
+    /// let mut lexer = Lexer::new(r"'a' '\n' 'bc'".into());
+    /// assert_eq!(lexer.next_token(), Ok(Some(Token::Char('a'))));
+    /// assert_eq!(lexer.next_token(), Ok(Some(Token::Char('\n'))));
+    /// assert_eq!(lexer.next_token(), Err(LexError::InvalidChar(Span { line: 1, col: 10 })));
+    /// ```
+    pub fn next_token(&mut self) -> Result<Option<Token>, LexError> {
         while let Some(ch) = self.advance() {
+            self.token_start = self.position - 1;
             match ch {
+                '0' if matches!(self.current_char(), 'x' | 'X' | 'o' | 'O' | 'b' | 'B') => {
+                    let radix = match self.current_char().to_ascii_lowercase() {
+                        'x' => 16,
+                        'o' => 8,
+                        _ => 2,
+                    };
+                    self.advance();
+                    let mut digits = String::new();
+                    while self.current_char().is_ascii_alphanumeric() {
+                        digits.extend(self.advance());
+                    }
+                    let number = IBig::from_str_radix(&digits, radix)
+                        .map_err(|_| LexError::InvalidNumber(self.span_at(self.token_start)))?;
+                    return Ok(Some(Token::Number(number)));
+                }
                 '0'..='9' => {
                     let mut num = String::new();
                     num.push(ch);
-                    while let Some(ch) = self.advance() {
-                        if !ch.is_numeric() {
-                            self.position -= 1;
-                            break;
-                        }
-                        num.push(ch);
-                    }
-                    let number = IBig::from_str_radix(&num, 10).unwrap();
-                    return Some(Token::Number(number));
-                }
-                '+' => return Some(Token::Operator(Op::Add)),
-                '-' => return Some(Token::Operator(Op::Sub)),
-                '*' => return Some(Token::Operator(Op::Mul)),
-                '/' => return Some(Token::Operator(Op::Div)),
-                '(' => return Some(Token::LParen),
-                ')' => return Some(Token::RParen),
-                '=' => {
-                    if self.current_char() == '=' {
+                    self.read_digits(&mut num);
+                    let mut is_float = false;
+                    if self.current_char() == '.' && self.char_at(1).is_ascii_digit() {
+                        is_float = true;
+                        num.push('.');
                         self.advance();
-                        return Some(Token::Operator(Op::Eq));
+                        self.read_digits(&mut num);
                     }
-                    return Some(Token::Assign);
-                }
-                '!' => {
-                    if self.current_char() == '=' {
-                        self.advance();
-                        return Some(Token::Operator(Op::Ne));
+                    if matches!(self.current_char(), 'e' | 'E') {
+                        let sign = usize::from(matches!(self.char_at(1), '+' | '-'));
+                        if self.char_at(1 + sign).is_ascii_digit() {
+                            is_float = true;
+                            for _ in 0..=sign {
+                                num.extend(self.advance());
+                            }
+                            self.read_digits(&mut num);
+                        }
                     }
-                    panic!("Unexpected charactor {}!", ch)
-                }
-                '>' => {
-                    if self.current_char() == '=' {
-                        self.advance();
-                        return Some(Token::Operator(Op::Ge));
-                    } else if self.current_char() == '>' {
-                        self.advance();
-                        return Some(Token::Operator(Op::RShift));
+                    if is_float {
+                        // Any digits, a dot and an exponent make a valid float.
+                        return Ok(Some(Token::Float(num.parse().unwrap())));
                     }
-                    return Some(Token::Operator(Op::Gt));
+                    let number = IBig::from_str_radix(&num, 10).unwrap();
+                    return Ok(Some(Token::Number(number)));
                 }
-                '<' => {
-                    if self.current_char() == '=' {
+                '+' | '-' | '*' | '/' | '%' | '=' | '!' | '<' | '>' | '|' | '&' | '^' => {
+                    let pair: String = [ch, self.current_char()].into_iter().collect();
+                    if let Some(op) = Op::from_str(&pair) {
                         self.advance();
-                        return Some(Token::Operator(Op::Le));
-                    } else if self.current_char() == '<' {
-                        self.advance();
-                        return Some(Token::Operator(Op::LShift));
+                        return Ok(Some(Token::Operator(op)));
                     }
-                    return Some(Token::Operator(Op::Lt));
-                }
-                '|' => {
-                    if self.current_char() == '|' {
-                        self.advance();
-                        return Some(Token::Operator(Op::Or));
+                    if ch == '=' {
+                        return Ok(Some(Token::Assign));
                     }
-                    panic!("Unexpected charactor {}!", ch)
+                    let op = Op::from_str(ch.encode_utf8(&mut [0; 4]))
+                        .ok_or(LexError::UnexpectedChar(ch, self.span_at(self.token_start)))?;
+                    return Ok(Some(Token::Operator(op)));
                 }
-                '&' => {
-                    if self.current_char() == '&' {
-                        self.advance();
-                        return Some(Token::Operator(Op::And));
-                    }
-                    panic!("Unexpected charactor {}!", ch)
+                '(' => return Ok(Some(Token::LParen)),
+                ')' => return Ok(Some(Token::RParen)),
+                ';' => return Ok(Some(Token::Semi)),
+                '?' => return Ok(Some(Token::Question)),
+                ':' => return Ok(Some(Token::Colon)),
+                '{' => return Ok(Some(Token::LBrace)),
+                '}' => return Ok(Some(Token::RBrace)),
+                '[' => return Ok(Some(Token::LBracket)),
+                ']' => return Ok(Some(Token::RBracket)),
+                ',' => return Ok(Some(Token::Comma)),
+                '"' => {
+                    let string = self.read_string()?;
+                    return Ok(Some(Token::Str(self.intern(string))));
                 }
-                ';' => return Some(Token::Semi),
-                '{' => return Some(Token::LBrace),
-                '}' => return Some(Token::RBrace),
-                '[' => return Some(Token::LBracket),
-                ']' => return Some(Token::RBracket),
-                ',' => return Some(Token::Comma),
+                '\'' => return Ok(Some(Token::Char(self.read_char()?))),
                 ' ' | '\n' | '\r' => continue,
                 _ => {
                     if ch.is_alphabetic() || ch == '_' {
@@ -236,23 +461,46 @@ impl Lexer {
                             id.push(ch);
                         }
                         if let Some(keyword_type) = KeywordTypes::from_string(&id) {
-                            return Some(Token::Keyword(keyword_type));
+                            return Ok(Some(Token::Keyword(keyword_type)));
                         }
 
-                        if let Some(n) = self.strings.get(&id) {
-                            return Some(Token::Id(*n));
-                        } else {
-                            let n = self.next_id;
-                            self.string_table.push(id.clone());
-                            self.strings.insert(id, n);
-                            self.next_id += 1;
-                            return Some(Token::Id(n));
-                        }
+                        return Ok(Some(Token::Id(self.intern(id))));
                     }
-                    panic!("Unexpected charactor {}!", ch)
+                    return Err(LexError::UnexpectedChar(ch, self.span_at(self.token_start)));
                 }
             }
         }
-        None
+        Ok(None)
+    }
+}
+
+/// Yields the tokens of the input like [`Lexer::get_token`], so it also panics on
+/// malformed input. \
+/// Example
+/// ```rust
+/// use cara::ast::Op;
+/// use cara::frontend::{Lexer, Token};
+///
+/// let tokens = Lexer::new("a = 1 + 2;".into()).collect::<Vec<_>>();
+///
+/// let mut lexer = Lexer::new("a = 1 + 2;".into());
+/// let mut expected = Vec::new();
+/// while let Some(token) = lexer.get_token() {
+///     expected.push(token);
+/// }
+/// assert_eq!(tokens, expected);
+/// assert_eq!(tokens[3], Token::Operator(Op::Add));
+///
+/// let numbers = Lexer::new("1 + 2 * 3".into()).filter_map(|token| match token {
+///     Token::Number(number) => Some(number),
+///     _ => None,
+/// });
+/// assert_eq!(numbers.count(), 3);
+/// ```
+impl Iterator for Lexer {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        self.get_token()
     }
 }
@@ -1,5 +1,11 @@
 mod lexer;
 mod parser;
+mod precedence;
+mod result;
+mod span;
 
 pub use lexer::*;
 pub use parser::*;
+pub use precedence::*;
+pub use result::{LexError, ParseError};
+pub use span::Span;
@@ -1,13 +1,29 @@
-use alloc::{string::String, vec::Vec};
+use alloc::{string::String, vec, vec::Vec};
 use dashu_int::IBig;
 
-use super::{KeywordTypes, Lexer, Token};
+use super::result::{ParseError, Result};
+use super::{Assoc, KeywordTypes, Lexer, PrecedenceTable, Token};
 use crate::ast::{AstNodes, Op};
 
-/// This is a simple and stupid LL(1) parser.
+/// This is a simple and stupid LL(1) parser. \
+/// Whitespace between tokens doesn't matter, so calls and indexing may have spaces
+/// before their parentheses or brackets.
+/// ```rust
+/// use cara::backend::CrValue;
+///
+/// let src = "fn f(x) { return x; } fn g(a) { return f (a [1]); } g([1, 2])";
+/// assert_eq!(cara::eval(src).unwrap(), CrValue::Number(2.into()));
+///
+/// let src = "fn f() { return 1; } var arr = [3]; f (); f(); arr [0]; arr[0] = 4; arr [0]";
+/// assert_eq!(cara::eval(src).unwrap(), CrValue::Number(4.into()));
+/// ```
 pub struct Parser {
     pub lexer: Lexer,
     current_token: Option<Token>,
+    position: usize,
+    /// The token after the current one and its position, once [`Parser::peek`] read it.
+    peeked: Option<(Option<Token>, usize)>,
+    precedence: PrecedenceTable,
 }
 
 impl Parser {
@@ -19,19 +35,72 @@ impl Parser {
     /// let mut parser = Parser::new(lexer);
     /// ```
     #[must_use]
-    pub fn new(mut lexer: Lexer) -> Self {
-        let tok = lexer.get_token();
+    pub fn new(lexer: Lexer) -> Self {
+        Self::with_precedence(lexer, PrecedenceTable::default())
+    }
+
+    /// Creates a parser which uses a custom operator precedence table. \
+    /// Example
+    /// ```rust
+    /// use cara::ast::{AstNodes, Op};
+    /// use cara::frontend::{Assoc, Lexer, Parser, PrecedenceTable};
+    ///
+    /// let mut table = PrecedenceTable::default();
+    /// table.set(Op::Sub, 6, Assoc::Right);
+    ///
+    /// let lexer = Lexer::new("var a = 8 - 4 - 2;".into());
+    /// let mut parser = Parser::with_precedence(lexer, table);
+    /// let (ast, _) = parser.parse_compile_unit().unwrap();
+    ///
+    /// // Parsed as `8 - (4 - 2)`.
+    /// let AstNodes::CompileUnit(statements) = ast else { unreachable!() };
+    /// let AstNodes::VarDef(_, value) = &statements[0] else { unreachable!() };
+    /// let AstNodes::BinaryOp(_, Op::Sub, right, _) = value.as_ref() else { unreachable!() };
+    /// assert!(matches!(right.as_ref(), AstNodes::BinaryOp(_, Op::Sub, ..)));
+    /// ```
+    #[must_use]
+    pub const fn with_precedence(lexer: Lexer, precedence: PrecedenceTable) -> Self {
         Self {
             lexer,
-            current_token: tok,
+            current_token: None,
+            position: 0,
+            peeked: None,
+            precedence,
+        }
+    }
+
+    /// Returns the operator precedence table, which can be changed before parsing.
+    pub fn precedence_mut(&mut self) -> &mut PrecedenceTable {
+        &mut self.precedence
+    }
+
+    fn advance(&mut self) -> Result<()> {
+        (self.current_token, self.position) = match self.peeked.take() {
+            Some(peeked) => peeked,
+            None => (self.lexer.next_token()?, self.lexer.token_start()),
+        };
+        Ok(())
+    }
+
+    /// Returns the token after the current one without consuming anything.
+    fn peek(&mut self) -> Result<Option<&Token>> {
+        if self.peeked.is_none() {
+            self.peeked = Some((self.lexer.next_token()?, self.lexer.token_start()));
         }
+        Ok(self.peeked.as_ref().and_then(|(token, _)| token.as_ref()))
     }
 
-    fn advance(&mut self) {
-        self.current_token = self.lexer.get_token();
+    fn unexpected(&self) -> ParseError {
+        match self.current_token.clone() {
+            Some(found) => ParseError::UnexpectedToken {
+                found,
+                position: self.lexer.span_at(self.position),
+            },
+            None => ParseError::UnexpectedEof,
+        }
     }
 
-    fn eat(&mut self, token: Token) -> Token {
+    fn eat(&mut self, token: Token) -> Result<Token> {
         if let Some(t) = self.current_token.as_ref() {
             let ok = match (t, token.clone()) {
                 (Token::Id(_), Token::Id(_))
@@ -41,486 +110,689 @@ impl Parser {
             };
             if ok {
                 let t = t.clone();
-                self.advance();
-                t
+                self.advance()?;
+                Ok(t)
             } else {
-                panic!("Expected {:?}, but found {:?}", token, t);
+                Err(ParseError::Expected {
+                    expected: token,
+                    found: t.clone(),
+                    position: self.lexer.span_at(self.position),
+                })
             }
         } else {
-            panic!("Unexpected end of input");
+            Err(ParseError::UnexpectedEof)
         }
     }
 
-    /// Returns the whole AST. \
+    fn eat_ident(&mut self) -> Result<usize> {
+        Ok(self.eat(Token::Id(0))?.as_ident().unwrap())
+    }
+
+    /// Returns the whole AST and the string table. \
     /// ### Expample
     /// ```rust
     /// use cara::frontend::{Lexer, Parser};
-    /// let lexer = Lexer::new("1-(5+7)/2+2*3-100".into());
+    /// let lexer = Lexer::new("var a = 1-(5+7)/2+2*3-100;".into());
     /// let mut parser = Parser::new(lexer);
-    /// let ast = parser.parse_compile_unit();
-    /// println!("{:#?}",ast);
+    /// let (ast, strings) = parser.parse_compile_unit().unwrap();
+    /// println!("{:#?}", ast);
     /// ```
     ///
     /// ### Output:
-    /// ```
+    /// ```text
     /// CompileUnit(
-    ///     BinaryOp(
-    ///         BinaryOp(
+    ///     [
+    ///         VarDef(
+    ///             0,
     ///             BinaryOp(
-    ///                 Number(
-    ///                     1,
-    ///                 ),
-    ///                 '-',
     ///                 BinaryOp(
     ///                     BinaryOp(
     ///                         Number(
-    ///                             5,
+    ///                             1,
     ///                         ),
-    ///                         '+',
-    ///                         Number(
-    ///                             7,
+    ///                         Sub,
+    ///                         BinaryOp(
+    ///                             BinaryOp(
+    ///                                 Number(
+    ///                                     5,
+    ///                                 ),
+    ///                                 Add,
+    ///                                 Number(
+    ///                                     7,
+    ///                                 ),
+    ///                                 Span {
+    ///                                     line: 1,
+    ///                                     col: 13,
+    ///                                 },
+    ///                             ),
+    ///                             Div,
+    ///                             Number(
+    ///                                 2,
+    ///                             ),
+    ///                             Span {
+    ///                                 line: 1,
+    ///                                 col: 16,
+    ///                             },
     ///                         ),
+    ///                         Span {
+    ///                             line: 1,
+    ///                             col: 10,
+    ///                         },
     ///                     ),
-    ///                     '/',
-    ///                     Number(
-    ///                         2,
+    ///                     Add,
+    ///                     BinaryOp(
+    ///                         Number(
+    ///                             2,
+    ///                         ),
+    ///                         Mul,
+    ///                         Number(
+    ///                             3,
+    ///                         ),
+    ///                         Span {
+    ///                             line: 1,
+    ///                             col: 20,
+    ///                         },
     ///                     ),
+    ///                     Span {
+    ///                         line: 1,
+    ///                         col: 18,
+    ///                     },
     ///                 ),
-    ///             ),
-    ///             '+',
-    ///             BinaryOp(
-    ///                 Number(
-    ///                     2,
-    ///                 ),
-    ///                 '*',
+    ///                 Sub,
     ///                 Number(
-    ///                     3,
+    ///                     100,
     ///                 ),
+    ///                 Span {
+    ///                     line: 1,
+    ///                     col: 22,
+    ///                 },
     ///             ),
     ///         ),
-    ///         '-',
-    ///         Number(
-    ///             100,
-    ///         ),
-    ///     ),
-    /// )*/
+    ///     ],
+    /// )
     /// ```
-    pub fn parse_compile_unit(&mut self) -> (AstNodes, Vec<String>) {
+    pub fn parse_compile_unit(&mut self) -> Result<(AstNodes, Vec<String>)> {
+        self.advance()?;
         let mut children = Vec::new();
         while self.current_token.is_some() {
-            children.push(self.parse_statement());
+            children.push(self.parse_statement()?);
         }
-        (AstNodes::CompileUnit(children), self.lexer.string_table())
+        Ok((AstNodes::CompileUnit(children), self.lexer.string_table()))
     }
 
-    fn parse_statement(&mut self) -> AstNodes {
-        if let Some(current_token) = self.current_token.clone() {
-            match current_token {
-                Token::Keyword(key_word) => {
-                    return match key_word {
-                        KeywordTypes::Var => self.parse_var(),
-                        KeywordTypes::Const => self.parse_const(),
-                        KeywordTypes::Fn => self.parse_function(),
-                        KeywordTypes::Return => self.parse_return(),
-                        KeywordTypes::If => self.parse_if(),
-                        KeywordTypes::For => self.parse_for(),
-                        KeywordTypes::Break => self.parse_break(),
-                        KeywordTypes::Continue => self.parse_continue(),
-                        KeywordTypes::While => self.parse_while(),
-                        _ => unreachable!(),
-                    }
-                }
-                Token::Id(_) => {
-                    if self.lexer.current_char() == '(' {
-                        return self.parse_call(true);
-                    } else {
-                        return self.parse_assign();
-                    }
+    /// Parses the whole input like [`Parser::parse_compile_unit`], but returns an AST
+    /// which holds the names of identifiers and string literals instead of their ids. \
+    /// Example
+    /// ```rust
+    /// use cara::ast::AstNodes;
+    /// use cara::frontend::{Lexer, Parser};
+    ///
+    /// let lexer = Lexer::new("var foo = 1;".into());
+    /// let ast = Parser::new(lexer).parse_named().unwrap();
+    ///
+    /// let AstNodes::CompileUnit(statements) = ast else { unreachable!() };
+    /// assert!(matches!(&statements[0], AstNodes::VarDef(name, _) if name == "foo"));
+    /// ```
+    pub fn parse_named(&mut self) -> Result<AstNodes<String>> {
+        let (ast, strings) = self.parse_compile_unit()?;
+        Ok(ast.map_ids(&mut |id| strings[id].clone()))
+    }
+
+    fn parse_statement(&mut self) -> Result<AstNodes> {
+        match self.current_token.clone() {
+            Some(Token::Keyword(key_word)) => match key_word {
+                KeywordTypes::Var => self.parse_var(),
+                KeywordTypes::Const => self.parse_const(),
+                KeywordTypes::Fn if self.peek()? == Some(&Token::LParen) => {
+                    self.parse_expr_statement()
                 }
-                _ => panic!("Syntax error {:?}!", current_token),
-            }
+                KeywordTypes::Fn => self.parse_function(),
+                KeywordTypes::Return => self.parse_return(),
+                KeywordTypes::If => self.parse_if(),
+                KeywordTypes::For => self.parse_for(),
+                KeywordTypes::Break => self.parse_break(),
+                KeywordTypes::Continue => self.parse_continue(),
+                KeywordTypes::While => self.parse_while(),
+                KeywordTypes::Loop => self.parse_loop(),
+                KeywordTypes::True | KeywordTypes::False => self.parse_expr_statement(),
+                _ => Err(self.unexpected()),
+            },
+            Some(_) => self.parse_expr_statement(),
+            None => Err(ParseError::UnexpectedEof),
+        }
+    }
+
+    /// Parses an expression used as a statement, or an assignment if the expression
+    /// is followed by `=`. The semicolon may be left out after the last statement of a
+    /// block, whose value it then becomes.
+    fn parse_expr_statement(&mut self) -> Result<AstNodes> {
+        let expr = self.parse_expr()?;
+        if self.current_token == Some(Token::Assign) {
+            return self.parse_assign(expr);
+        }
+        if self.current_token == Some(Token::Comma) {
+            return self.parse_unpack(expr);
+        }
+        if !matches!(self.current_token, None | Some(Token::RBrace)) {
+            self.eat(Token::Semi)?;
         }
-        panic!("Nothing to parse!");
+        Ok(expr)
     }
 
-    fn parse_break(&mut self) -> AstNodes {
-        self.advance();
-        AstNodes::Break
+    fn parse_break(&mut self) -> Result<AstNodes> {
+        self.advance()?;
+        let value = match self.current_token {
+            None | Some(Token::Semi | Token::RBrace) => None,
+            _ => Some(self.parse_expr()?.into()),
+        };
+        self.skip_semi()?;
+        Ok(AstNodes::Break(value))
     }
 
-    fn parse_continue(&mut self) -> AstNodes {
-        self.advance();
-        AstNodes::Continue
+    fn parse_continue(&mut self) -> Result<AstNodes> {
+        self.advance()?;
+        self.skip_semi()?;
+        Ok(AstNodes::Continue)
     }
 
-    fn parse_while(&mut self) -> AstNodes {
-        self.advance();
-        let condition = self.parse_expr();
-        self.eat(Token::LBrace);
-        let body = self.parse_block();
-        self.eat(Token::RBrace);
-        AstNodes::While(condition.into(), body)
+    /// Consumes a semicolon if there is one.
+    fn skip_semi(&mut self) -> Result<()> {
+        if self.current_token == Some(Token::Semi) {
+            self.advance()?;
+        }
+        Ok(())
     }
 
-    fn parse_list(&mut self) -> AstNodes {
-        self.eat(Token::LBracket);
+    fn parse_while(&mut self) -> Result<AstNodes> {
+        self.advance()?;
+        let condition = self.parse_expr()?;
+        self.eat(Token::LBrace)?;
+        let body = self.parse_block()?;
+        self.eat(Token::RBrace)?;
+        Ok(AstNodes::While(condition.into(), body))
+    }
+
+    fn parse_loop(&mut self) -> Result<AstNodes> {
+        self.advance()?;
+        self.eat(Token::LBrace)?;
+        let body = self.parse_block()?;
+        self.eat(Token::RBrace)?;
+        Ok(AstNodes::Loop(body))
+    }
+
+    fn parse_map(&mut self) -> Result<AstNodes> {
+        self.eat(Token::LBrace)?;
+        let mut entries = Vec::new();
+        while self.current_token != Some(Token::RBrace) {
+            let key = self.parse_expr()?;
+            self.eat(Token::Colon)?;
+            let value = self.parse_expr()?;
+            entries.push((key, value));
+            if self.current_token == Some(Token::Comma) {
+                self.advance()?;
+            } else {
+                break;
+            }
+        }
+        self.eat(Token::RBrace)?;
+        Ok(AstNodes::Map(entries))
+    }
+
+    fn parse_list(&mut self) -> Result<AstNodes> {
+        self.eat(Token::LBracket)?;
         let mut value_list = Vec::new();
 
         if self.current_token != Some(Token::RBracket) {
-            let first_value = self.parse_expr();
+            let first_value = self.parse_expr()?;
 
             if self.current_token == Some(Token::Semi) {
-                self.advance();
-                let num = self.parse_expr();
-                self.eat(Token::RBracket);
-                return AstNodes::TemplateList(first_value.into(), num.into());
+                self.advance()?;
+                let num = self.parse_expr()?;
+                self.eat(Token::RBracket)?;
+                return Ok(AstNodes::TemplateList(first_value.into(), num.into()));
             } else {
                 value_list.push(first_value);
                 while let Some(token) = self.current_token.clone() {
                     if token == Token::RBracket {
                         break;
                     }
-                    self.eat(Token::Comma);
-                    let value = self.parse_expr();
+                    self.eat(Token::Comma)?;
+                    let value = self.parse_expr()?;
                     value_list.push(value);
                 }
             }
         }
 
-        self.eat(Token::RBracket);
-        AstNodes::List(value_list)
+        self.eat(Token::RBracket)?;
+        Ok(AstNodes::List(value_list))
     }
 
-    fn parse_for(&mut self) -> AstNodes {
-        self.advance();
+    fn parse_for(&mut self) -> Result<AstNodes> {
+        self.advance()?;
+
+        let variable = self.eat_ident()?;
 
-        let variable = self.eat(Token::Id(0)).as_ident().unwrap();
+        self.eat(Token::Keyword(KeywordTypes::In))?;
 
-        self.eat(Token::Keyword(KeywordTypes::In));
+        // A range is in parentheses and has a comma, anything else is a list.
+        if self.current_token != Some(Token::LParen) {
+            let list = self.parse_expr()?;
+            self.eat(Token::LBrace)?;
+            let body = self.parse_block()?;
+            self.eat(Token::RBrace)?;
+            return Ok(AstNodes::ForEach(variable, list.into(), body));
+        }
 
-        self.eat(Token::LParen);
-        let start = self.parse_expr();
-        self.eat(Token::Comma);
-        let end = self.parse_expr();
+        self.eat(Token::LParen)?;
+        let start = self.parse_expr()?;
+        if self.current_token == Some(Token::RParen) {
+            // The parentheses only began the list, as in `for x in (xs)[0] { ... }`.
+            self.advance()?;
+            let list = self.parse_postfix(start)?;
+            let list = self.parse_binary_rest(list, 0)?;
+            let list = self.parse_ternary_rest(list)?;
+            self.eat(Token::LBrace)?;
+            let body = self.parse_block()?;
+            self.eat(Token::RBrace)?;
+            return Ok(AstNodes::ForEach(variable, list.into(), body));
+        }
+        self.eat(Token::Comma)?;
+        let end = self.parse_expr()?;
 
         let step = if self.current_token == Some(Token::Comma) {
-            self.advance();
-            self.parse_expr()
+            self.advance()?;
+            self.parse_expr()?
         } else {
             AstNodes::Number(IBig::from(1))
         };
 
-        self.eat(Token::RParen);
+        self.eat(Token::RParen)?;
 
-        self.eat(Token::LBrace);
-        let body = self.parse_block();
-        self.eat(Token::RBrace);
+        self.eat(Token::LBrace)?;
+        let body = self.parse_block()?;
+        self.eat(Token::RBrace)?;
 
-        AstNodes::For(variable, start.into(), end.into(), step.into(), body)
+        Ok(AstNodes::For(
+            variable,
+            start.into(),
+            end.into(),
+            step.into(),
+            body,
+        ))
     }
 
-    fn parse_if(&mut self) -> AstNodes {
-        self.advance();
+    fn parse_if(&mut self) -> Result<AstNodes> {
+        self.advance()?;
         //self.eat(Token::LParen);
-        let condition = self.parse_expr();
+        let condition = self.parse_expr()?;
         //self.eat(Token::RParen);
 
-        self.eat(Token::LBrace);
-        let then_block = self.parse_block();
-        self.eat(Token::RBrace);
-
-        let else_block = if self.current_token == Some(Token::Keyword(KeywordTypes::Else)) {
-            self.advance();
-            self.eat(Token::LBrace);
-            let block = self.parse_block();
-            self.eat(Token::RBrace);
-            block
-        } else {
-            Vec::new()
+        self.eat(Token::LBrace)?;
+        let then_block = self.parse_block()?;
+        self.eat(Token::RBrace)?;
+
+        let else_block = match self.current_token {
+            Some(Token::Keyword(KeywordTypes::Else)) => {
+                self.advance()?;
+                if self.current_token == Some(Token::Keyword(KeywordTypes::If)) {
+                    return Ok(AstNodes::If(
+                        condition.into(),
+                        then_block,
+                        vec![self.parse_if()?],
+                    ));
+                }
+                self.eat(Token::LBrace)?;
+                let block = self.parse_block()?;
+                self.eat(Token::RBrace)?;
+                block
+            }
+            // `elif`, like `else if`, starts another `if` chained in the else block.
+            Some(Token::Keyword(KeywordTypes::Elif)) => vec![self.parse_if()?],
+            _ => Vec::new(),
         };
 
-        AstNodes::If(condition.into(), then_block, else_block)
+        Ok(AstNodes::If(condition.into(), then_block, else_block))
     }
 
-    fn parse_block(&mut self) -> Vec<AstNodes> {
+    fn parse_block(&mut self) -> Result<Vec<AstNodes>> {
         let mut children = Vec::new();
         while self.current_token.is_some() {
             if self.current_token == Some(Token::RBrace) {
                 break;
             }
-            children.push(self.parse_statement());
+            children.push(self.parse_statement()?);
         }
-        children
+        Ok(children)
     }
 
-    fn parse_return(&mut self) -> AstNodes {
-        self.advance();
-        let expr = self.parse_expr();
-        self.eat(Token::Semi);
-        AstNodes::Return(expr.into())
+    fn parse_return(&mut self) -> Result<AstNodes> {
+        self.advance()?;
+        let value = match self.current_token {
+            Some(Token::Semi) => None,
+            _ => Some(self.parse_expr()?.into()),
+        };
+        self.eat(Token::Semi)?;
+        Ok(AstNodes::Return(value))
     }
 
-    fn parse_function(&mut self) -> AstNodes {
-        self.advance();
-        let id = self.eat(Token::Id(0)).as_ident().unwrap();
+    fn parse_function(&mut self) -> Result<AstNodes> {
+        self.advance()?;
+        let id = self.eat_ident()?;
+        let (params, body) = self.parse_function_rest()?;
+        Ok(AstNodes::FunctionDef(id, params, body))
+    }
 
-        self.eat(Token::LParen);
-        let params = self.parse_params();
-        self.eat(Token::RParen);
+    fn parse_lambda(&mut self) -> Result<AstNodes> {
+        self.advance()?;
+        let (params, body) = self.parse_function_rest()?;
+        Ok(AstNodes::Lambda(params, body))
+    }
 
-        self.eat(Token::LBrace);
+    /// Parses the parameters and body of a function.
+    fn parse_function_rest(&mut self) -> Result<(Vec<usize>, Vec<AstNodes>)> {
+        self.eat(Token::LParen)?;
+        let params = self.parse_params()?;
+        self.eat(Token::RParen)?;
+
+        self.eat(Token::LBrace)?;
 
         let mut body = Vec::new();
         while let Some(current) = self.current_token.clone() {
             if current == Token::RBrace {
                 break;
             } else {
-                body.push(self.parse_statement());
+                body.push(self.parse_statement()?);
             }
         }
 
-        self.eat(Token::RBrace);
+        self.eat(Token::RBrace)?;
 
-        AstNodes::FunctionDef(id, params, body)
+        Ok((params, body))
     }
 
-    fn parse_params(&mut self) -> Vec<usize> {
+    fn parse_params(&mut self) -> Result<Vec<usize>> {
         let mut params = Vec::new();
         while let Some(current_token) = self.current_token.clone() {
             match current_token {
                 Token::Id(id) => {
                     params.push(id);
-                    self.advance();
-                    if let Some(token) = self.current_token.clone() {
-                        match token {
-                            Token::Comma => self.advance(),
-                            Token::RParen => break,
-                            _ => panic!("Expected identifier or ',', found {token:?}!"),
-                        }
+                    self.advance()?;
+                    match self.current_token {
+                        Some(Token::Comma) => self.advance()?,
+                        Some(Token::RParen) => break,
+                        _ => return Err(self.unexpected()),
                     }
                 }
                 Token::RParen => break,
-                _ => panic!(
-                    "Syntax error! Expected ID or ',', found {:?}!",
-                    current_token
-                ),
+                _ => return Err(self.unexpected()),
             }
         }
-        params
+        Ok(params)
     }
 
-    fn parse_const(&mut self) -> AstNodes {
-        self.advance();
+    fn parse_const(&mut self) -> Result<AstNodes> {
+        self.advance()?;
 
-        let id = self.eat(Token::Id(0)).as_ident().unwrap();
+        let id = self.eat_ident()?;
 
-        self.eat(Token::Assign);
+        self.eat(Token::Assign)?;
 
-        let init_val = self.parse_expr();
+        let init_val = self.parse_expr()?;
 
-        self.eat(Token::Semi);
+        self.eat(Token::Semi)?;
 
-        AstNodes::ConstDef(id, init_val.into())
+        Ok(AstNodes::ConstDef(id, init_val.into()))
     }
 
-    fn parse_var(&mut self) -> AstNodes {
-        self.advance();
+    fn parse_var(&mut self) -> Result<AstNodes> {
+        self.advance()?;
 
-        let id = self.eat(Token::Id(0)).as_ident().unwrap();
+        let id = self.eat_ident()?;
+        if self.current_token == Some(Token::Comma) {
+            let mut ids = vec![id];
+            while self.current_token == Some(Token::Comma) {
+                self.advance()?;
+                ids.push(self.eat_ident()?);
+            }
+            self.eat(Token::Assign)?;
+            let value = self.parse_values()?;
+            self.eat(Token::Semi)?;
+            return Ok(AstNodes::VarUnpack(ids, value.into()));
+        }
 
-        self.eat(Token::Assign);
+        self.eat(Token::Assign)?;
 
-        let init_val = if self.current_token == Some(Token::LBracket) {
-            self.parse_list()
-        } else {
-            self.parse_expr()
-        };
+        let init_val = self.parse_expr()?;
 
-        self.eat(Token::Semi);
+        self.eat(Token::Semi)?;
 
-        AstNodes::VarDef(id, init_val.into())
+        Ok(AstNodes::VarDef(id, init_val.into()))
     }
 
-    fn parse_assign(&mut self) -> AstNodes {
-        let id = self.eat(Token::Id(0)).as_ident().unwrap();
+    /// Parses the rest of an assignment to several variables, the first of which is
+    /// `first`.
+    fn parse_unpack(&mut self, first: AstNodes) -> Result<AstNodes> {
+        let mut targets = vec![first];
+        while self.current_token == Some(Token::Comma) {
+            self.advance()?;
+            targets.push(self.parse_expr()?);
+        }
+        let ids = targets
+            .into_iter()
+            .map(|target| match target {
+                AstNodes::ReadVar(id) => Ok(id),
+                _ => Err(self.unexpected()),
+            })
+            .collect::<Result<Vec<usize>>>()?;
+        self.eat(Token::Assign)?;
+        let value = self.parse_values()?;
+        self.eat(Token::Semi)?;
+        Ok(AstNodes::Unpack(ids, value.into()))
+    }
 
-        let index = if self.current_token == Some(Token::LBracket) {
-            self.advance();
-            let index = self.parse_expr();
-            self.eat(Token::RBracket);
-            Some(index.into())
-        } else {
-            None
+    /// Parses the right side of an assignment to several variables, where several
+    /// values separated by commas make a list.
+    fn parse_values(&mut self) -> Result<AstNodes> {
+        let first = self.parse_expr()?;
+        if self.current_token != Some(Token::Comma) {
+            return Ok(first);
+        }
+        let mut values = vec![first];
+        while self.current_token == Some(Token::Comma) {
+            self.advance()?;
+            values.push(self.parse_expr()?);
+        }
+        Ok(AstNodes::List(values))
+    }
+
+    /// Parses the rest of an assignment to `target`, which must be a variable or an
+    /// element of one.
+    fn parse_assign(&mut self, target: AstNodes) -> Result<AstNodes> {
+        let mut indices = Vec::new();
+        let mut target = &target;
+        let id = loop {
+            match target {
+                AstNodes::ReadVar(id) => break *id,
+                AstNodes::Index(list, index, _) => {
+                    indices.push(index.as_ref().clone());
+                    target = list;
+                }
+                _ => return Err(self.unexpected()),
+            }
         };
+        indices.reverse();
 
-        self.eat(Token::Assign);
+        self.eat(Token::Assign)?;
 
-        let expr = self.parse_expr();
+        let expr = self.parse_expr()?;
 
-        self.eat(Token::Semi);
+        self.eat(Token::Semi)?;
 
-        AstNodes::Assign(id, index, expr.into())
+        Ok(AstNodes::Assign(id, indices, expr.into()))
     }
 
-    fn parse_expr(&mut self) -> AstNodes {
-        let mut node = self.parse_eq_expr();
-        while let Some(current_token) = self.current_token.clone() {
-            if let Some(op) = current_token.as_operator() {
-                match op {
-                    Op::Or | Op::And => {
-                        self.advance();
-                        node = AstNodes::BinaryOp(node.into(), op, self.parse_eq_expr().into());
-                    }
-                    _ => break,
-                }
-            } else {
-                break;
-            }
-        }
-        node
+    /// Parses an expression, which may be a ternary conditional.
+    fn parse_expr(&mut self) -> Result<AstNodes> {
+        let condition = self.parse_binary(0)?;
+        self.parse_ternary_rest(condition)
     }
 
-    fn parse_eq_expr(&mut self) -> AstNodes {
-        let mut node = self.parse_add_expr();
-        while let Some(current_token) = self.current_token.clone() {
-            if let Some(op) = current_token.as_operator() {
-                match op {
-                    Op::Eq | Op::Ne | Op::Ge | Op::Le | Op::Lt | Op::Gt => {
-                        self.advance();
-                        node = AstNodes::BinaryOp(node.into(), op, self.parse_add_expr().into());
-                    }
-                    _ => break,
-                }
-            } else {
-                break;
-            }
+    /// Parses the rest of a ternary conditional whose condition is already parsed,
+    /// if there is one.
+    fn parse_ternary_rest(&mut self, condition: AstNodes) -> Result<AstNodes> {
+        if self.current_token != Some(Token::Question) {
+            return Ok(condition);
         }
-        node
+        self.advance()?;
+        let then_value = self.parse_expr()?;
+        self.eat(Token::Colon)?;
+        let else_value = self.parse_expr()?;
+        Ok(AstNodes::Ternary(
+            condition.into(),
+            then_value.into(),
+            else_value.into(),
+        ))
     }
 
-    fn parse_add_expr(&mut self) -> AstNodes {
-        let mut node = self.parse_move_expr();
-        while let Some(current_token) = self.current_token.clone() {
-            if let Some(op) = current_token.as_operator() {
-                match op {
-                    Op::Add | Op::Sub => {
-                        self.advance();
-                        node = AstNodes::BinaryOp(node.into(), op, self.parse_move_expr().into());
-                    }
-                    _ => break,
-                }
-            } else {
-                break;
-            }
-        }
-        node
+    /// Parses binary operators binding at least as tight as `min_precedence`
+    /// by precedence climbing over the precedence table.
+    fn parse_binary(&mut self, min_precedence: u8) -> Result<AstNodes> {
+        let node = self.parse_factor()?;
+        self.parse_binary_rest(node, min_precedence)
     }
 
-    fn parse_move_expr(&mut self) -> AstNodes {
-        let mut node = self.parse_term();
-        while let Some(current_token) = self.current_token.clone() {
-            if let Some(op) = current_token.as_operator() {
-                match op {
-                    Op::LShift | Op::RShift => {
-                        self.advance();
-                        node = AstNodes::BinaryOp(node.into(), op, self.parse_term().into());
-                    }
-                    _ => break,
-                }
-            } else {
+    /// Parses binary operators following `node`, which is their first operand.
+    fn parse_binary_rest(&mut self, mut node: AstNodes, min_precedence: u8) -> Result<AstNodes> {
+        while let Some(op) = self.current_token.as_ref().and_then(Token::as_operator) {
+            let Some((precedence, assoc)) = self.precedence.get(op) else {
+                break;
+            };
+            if precedence < min_precedence {
                 break;
             }
+            let span = self.lexer.span_at(self.position);
+            self.advance()?;
+            let next_precedence = match assoc {
+                Assoc::Left => precedence.saturating_add(1),
+                Assoc::Right => precedence,
+            };
+            let right = self.parse_binary(next_precedence)?;
+            node = AstNodes::BinaryOp(node.into(), op, right.into(), span);
         }
-        node
+        Ok(node)
     }
 
-    fn parse_term(&mut self) -> AstNodes {
-        let mut node = self.parse_factor();
-        while let Some(current_token) = self.current_token.clone() {
-            if let Some(op) = current_token.as_operator() {
-                match op {
-                    Op::Mul | Op::Div | Op::Rem => {
-                        self.advance();
-                        node = AstNodes::BinaryOp(node.into(), op, self.parse_factor().into());
-                    }
-                    _ => break,
+    /// Parses a primary expression followed by any number of calls and indexings, so
+    /// the result of one can be used right away, as in `fns[0](1)` or `f()[0]`.
+    fn parse_factor(&mut self) -> Result<AstNodes> {
+        let node = self.parse_primary()?;
+        self.parse_postfix(node)
+    }
+
+    /// Parses the calls and indexings following `node`.
+    fn parse_postfix(&mut self, mut node: AstNodes) -> Result<AstNodes> {
+        loop {
+            node = match self.current_token {
+                Some(Token::LParen) => self.parse_call(node)?,
+                Some(Token::LBracket) => {
+                    let span = self.lexer.span_at(self.position);
+                    self.advance()?;
+                    let index_value = self.parse_expr()?;
+                    self.eat(Token::RBracket)?;
+                    AstNodes::Index(node.into(), index_value.into(), span)
                 }
-            } else {
-                break;
-            }
+                _ => return Ok(node),
+            };
         }
-        node
     }
 
-    fn parse_factor(&mut self) -> AstNodes {
-        let token = self.current_token.clone().unwrap();
+    fn parse_primary(&mut self) -> Result<AstNodes> {
+        let token = self
+            .current_token
+            .clone()
+            .ok_or(ParseError::UnexpectedEof)?;
         match token {
             Token::Number(num) => {
-                self.advance();
-                AstNodes::Number(num)
+                self.advance()?;
+                Ok(AstNodes::Number(num))
+            }
+            Token::Float(value) => {
+                self.advance()?;
+                Ok(AstNodes::Float(value))
+            }
+            Token::Keyword(KeywordTypes::True) => {
+                self.advance()?;
+                Ok(AstNodes::Bool(true))
+            }
+            Token::Keyword(KeywordTypes::False) => {
+                self.advance()?;
+                Ok(AstNodes::Bool(false))
+            }
+            Token::Str(id) => {
+                self.advance()?;
+                Ok(AstNodes::Str(id))
+            }
+            Token::Char(ch) => {
+                self.advance()?;
+                Ok(AstNodes::Char(ch))
             }
             Token::LParen => {
-                self.advance();
-                let node = self.parse_expr();
-                self.eat(Token::RParen);
-                node
+                self.advance()?;
+                let node = self.parse_expr()?;
+                self.eat(Token::RParen)?;
+                Ok(node)
+            }
+            Token::LBracket => self.parse_list(),
+            Token::LBrace => self.parse_map(),
+            Token::Keyword(KeywordTypes::Fn) => self.parse_lambda(),
+            Token::Keyword(KeywordTypes::Loop) => self.parse_loop(),
+            Token::Keyword(KeywordTypes::While) => self.parse_while(),
+            Token::Keyword(KeywordTypes::For) => self.parse_for(),
+            Token::Operator(op @ (Op::Add | Op::Sub)) => {
+                self.advance()?;
+                // The operand takes in `**` but no looser operator, so `-2 ** 2` is
+                // `-(2 ** 2)` while `-1 + 2` is `(-1) + 2`.
+                let precedence = self.precedence.get(Op::Pow).map_or(u8::MAX, |(p, _)| p);
+                let node = self.parse_binary(precedence)?;
+                Ok(AstNodes::UnaryOp(op, node.into()))
+            }
+            Token::Operator(Op::Not) => {
+                self.advance()?;
+                let node = self.parse_factor()?;
+                Ok(AstNodes::UnaryOp(Op::Not, node.into()))
             }
-            Token::Operator(op) => match op {
-                Op::Add | Op::Sub => {
-                    self.advance();
-                    let node = self.parse_expr();
-                    AstNodes::UnaryOp(op, node.into())
-                }
-                _ => panic!("Unexpected unary operator {:?}!", op),
-            },
             Token::Id(id) => {
-                if self.lexer.current_char() == '(' {
-                    self.parse_call(false)
-                } else if self.lexer.current_char() == '[' {
-                    self.advance();
-                    self.advance();
-                    let index_value = self.parse_expr();
-                    self.eat(Token::RBracket);
-                    AstNodes::Index(id, index_value.into())
-                } else {
-                    self.advance();
-                    AstNodes::ReadVar(id)
-                }
+                self.advance()?;
+                Ok(AstNodes::ReadVar(id))
             }
-            _ => panic!("Syntax error {:?}!", token),
+            _ => Err(self.unexpected()),
         }
     }
 
-    fn parse_call(&mut self, stmt: bool) -> AstNodes {
-        let id = self.eat(Token::Id(0)).as_ident().unwrap();
-
-        self.eat(Token::LParen);
+    fn parse_call(&mut self, callee: AstNodes) -> Result<AstNodes> {
+        self.eat(Token::LParen)?;
 
-        let args = self.parse_args();
+        let args = self.parse_args()?;
 
-        self.eat(Token::RParen);
-
-        if stmt {
-            self.eat(Token::Semi);
-        }
+        self.eat(Token::RParen)?;
 
-        AstNodes::Call(id, args)
+        Ok(AstNodes::Call(callee.into(), args))
     }
 
-    fn parse_args(&mut self) -> Vec<AstNodes> {
+    fn parse_args(&mut self) -> Result<Vec<AstNodes>> {
         let mut args = Vec::new();
         while let Some(current_token) = self.current_token.clone() {
             if current_token == Token::RParen {
                 break;
             } else {
-                args.push(self.parse_expr());
+                args.push(self.parse_expr()?);
                 if self.current_token == Some(Token::Comma) {
-                    self.advance();
+                    self.advance()?;
                 } else {
                     break;
                 }
             }
         }
-        args
+        Ok(args)
     }
 }
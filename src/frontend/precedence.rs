@@ -0,0 +1,95 @@
+use alloc::collections::BTreeMap;
+
+use crate::ast::Op;
+
+/// Associativity of a binary operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Assoc {
+    Left,
+    Right,
+}
+
+/// The precedence and associativity of every binary operator the parser knows.
+/// Operators with a higher precedence bind tighter.
+#[derive(Debug, Clone)]
+pub struct PrecedenceTable {
+    operators: BTreeMap<Op, (u8, Assoc)>,
+}
+
+impl PrecedenceTable {
+    /// Creates a table without any binary operators.
+    #[must_use]
+    pub const fn empty() -> Self {
+        Self {
+            operators: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the precedence and associativity of `op`, if it is a binary operator.
+    #[must_use]
+    pub fn get(&self, op: Op) -> Option<(u8, Assoc)> {
+        self.operators.get(&op).copied()
+    }
+
+    /// Sets the precedence and associativity of `op`.
+    pub fn set(&mut self, op: Op, precedence: u8, assoc: Assoc) {
+        self.operators.insert(op, (precedence, assoc));
+    }
+
+    /// Stops the parser from treating `op` as a binary operator.
+    pub fn remove(&mut self, op: Op) {
+        self.operators.remove(&op);
+    }
+}
+
+/// The default table keeps the precedence the parser always had: `&&` and `||` bind
+/// loosest, then comparisons, `+` and `-`, shifts and `*`, `/` and `%`, all from
+/// the left. The bitwise operators and `**` fit in around them. \
+/// Example
+/// ```rust
+/// use cara::ast::Op;
+/// use cara::frontend::{Assoc, PrecedenceTable};
+///
+/// let table = PrecedenceTable::default();
+/// let levels = [
+///     &[Op::Or, Op::And][..],
+///     &[Op::Eq, Op::Ne, Op::Ge, Op::Le, Op::Lt, Op::Gt],
+///     &[Op::Add, Op::Sub],
+///     &[Op::LShift, Op::RShift],
+///     &[Op::Mul, Op::Div, Op::Rem],
+/// ];
+/// for (looser, tighter) in levels.iter().zip(&levels[1..]) {
+///     for (a, b) in looser.iter().zip(tighter.iter()) {
+///         assert!(table.get(*a).unwrap().0 < table.get(*b).unwrap().0);
+///     }
+/// }
+/// for ops in levels {
+///     for op in ops {
+///         assert_eq!(table.get(*op), Some((table.get(ops[0]).unwrap().0, Assoc::Left)));
+///     }
+/// }
+///
+/// let value = cara::eval("[1 - 2 - 3, 1 + 2 << 1, 1 << 2 * 2, 1 < 2 == true, true || false && false]");
+/// assert_eq!(value.unwrap(), cara::eval("[-4, 5, 16, true, false]").unwrap());
+/// ```
+impl Default for PrecedenceTable {
+    fn default() -> Self {
+        let mut table = Self::empty();
+        for (ops, precedence) in [
+            (&[Op::Or, Op::And][..], 1),
+            (&[Op::Eq, Op::Ne, Op::Ge, Op::Le, Op::Lt, Op::Gt][..], 2),
+            (&[Op::BitOr][..], 3),
+            (&[Op::BitXor][..], 4),
+            (&[Op::BitAnd][..], 5),
+            (&[Op::Add, Op::Sub][..], 6),
+            (&[Op::LShift, Op::RShift][..], 7),
+            (&[Op::Mul, Op::Div, Op::Rem][..], 8),
+        ] {
+            for op in ops {
+                table.set(*op, precedence, Assoc::Left);
+            }
+        }
+        table.set(Op::Pow, 9, Assoc::Right);
+        table
+    }
+}
@@ -0,0 +1,125 @@
+use core::fmt;
+
+use super::{Span, Token};
+
+/// Error returned by the lexer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LexError {
+    /// A character which doesn't start any token.
+    UnexpectedChar(char, Span),
+    /// A string literal without its closing quote.
+    UnterminatedString(Span),
+    /// An unsupported escape sequence in a string literal.
+    UnknownEscape(char, Span),
+    /// A character literal which doesn't hold exactly one character.
+    InvalidChar(Span),
+    /// A hexadecimal, octal or binary literal with no digits or a digit its base
+    /// doesn't have. \
+    /// Example
+    /// ```rust
+    /// use cara::backend::CrValue;
+    /// use cara::frontend::{LexError, Span};
+    /// use cara::CaraError;
+    ///
+    /// let value = cara::eval("[0xFF == 255, 0o17 == 15, 0b1010 == 10, 0xff == 0XFF]").unwrap();
+    /// assert_eq!(value, cara::eval("[true, true, true, true]").unwrap());
+    /// let error = cara::eval("0xG").unwrap_err();
+    /// assert!(matches!(error, CaraError::Lex(LexError::InvalidNumber(Span { line: 1, col: 1 }))));
+    /// assert!(cara::eval("0b102").is_err());
+    /// assert!(cara::eval("0x").is_err());
+    /// ```
+    InvalidNumber(Span),
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnexpectedChar(ch, position) => {
+                write!(f, "unexpected character {ch:?} at {position}")
+            }
+            Self::UnterminatedString(position) => {
+                write!(f, "unterminated string starting at {position}")
+            }
+            Self::UnknownEscape(ch, position) => {
+                write!(f, "unknown escape sequence \\{ch} at {position}")
+            }
+            Self::InvalidChar(position) => {
+                write!(f, "invalid character literal at {position}")
+            }
+            Self::InvalidNumber(position) => {
+                write!(f, "invalid number literal at {position}")
+            }
+        }
+    }
+}
+
+/// Error returned by the parser. \
+/// Example
+/// ```rust
+/// use cara::frontend::{Lexer, ParseError, Parser, Span, Token};
+///
+/// let parse = |src: &str| Parser::new(Lexer::new(src.into())).parse_compile_unit();
+/// assert!(matches!(
+///     parse("var = ;"),
+///     Err(ParseError::Expected {
+///         expected: Token::Id(_),
+///         found: Token::Assign,
+///         position: Span { line: 1, col: 5 },
+///     })
+/// ));
+/// assert_eq!(
+///     parse("1 +;").unwrap_err(),
+///     ParseError::UnexpectedToken {
+///         found: Token::Semi,
+///         position: Span { line: 1, col: 4 },
+///     }
+/// );
+/// assert_eq!(parse("1 +").unwrap_err(), ParseError::UnexpectedEof);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    /// The lexer failed to read a token.
+    Lex(LexError),
+    /// A token was found where another one was expected.
+    Expected {
+        expected: Token,
+        found: Token,
+        position: Span,
+    },
+    /// A token was found which can't appear at its position.
+    UnexpectedToken { found: Token, position: Span },
+    /// The input ended in the middle of a construct.
+    UnexpectedEof,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Lex(error) => write!(f, "{error}"),
+            Self::Expected {
+                expected,
+                found,
+                position,
+            } => write!(f, "expected {expected}, but found {found} at {position}"),
+            Self::UnexpectedToken { found, position } => {
+                write!(f, "unexpected {found} at {position}")
+            }
+            Self::UnexpectedEof => write!(f, "unexpected end of input"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for LexError {}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseError {}
+
+impl From<LexError> for ParseError {
+    fn from(error: LexError) -> Self {
+        Self::Lex(error)
+    }
+}
+
+/// Result type of the parser.
+pub type Result<T> = core::result::Result<T, ParseError>;
@@ -0,0 +1,28 @@
+use core::fmt;
+
+/// A position in the source, counting lines and columns from 1. The default span,
+/// with both set to 0, stands for an unknown position. \
+/// Example
+/// ```rust
+/// use cara::frontend::{LexError, Span};
+/// use cara::CaraError;
+///
+/// let error = cara::eval("var a = 1;\nvar b = #;").unwrap_err();
+/// assert!(matches!(
+///     error,
+///     CaraError::Lex(LexError::UnexpectedChar('#', Span { line: 2, col: 9 }))
+/// ));
+/// assert_eq!(error.to_string(), "lexical error: unexpected character '#' at line 2, column 9");
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    pub line: usize,
+    pub col: usize,
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.col)
+    }
+}
@@ -1,12 +1,241 @@
-#![no_std]
-#![deny(unsafe_code)]
-
-extern crate alloc;
-
-#[cfg(feature = "snmalloc")]
-#[global_allocator]
-static GLOBAL: snmalloc_rs::SnMalloc = snmalloc_rs::SnMalloc;
-
-pub mod ast;
-pub mod backend;
-pub mod frontend;
+#![no_std]
+#![deny(unsafe_code)]
+
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+#[cfg(feature = "snmalloc")]
+#[global_allocator]
+static GLOBAL: snmalloc_rs::SnMalloc = snmalloc_rs::SnMalloc;
+
+pub mod ast;
+pub mod backend;
+pub mod frontend;
+
+use alloc::{boxed::Box, rc::Rc, string::String, vec::Vec};
+use core::cell::RefCell;
+use core::fmt::{self, Write};
+
+use ast::AstNodes;
+use backend::{CrValue, Interpreter};
+use frontend::{LexError, Lexer, ParseError, Parser};
+
+/// Error returned by [`eval`], covering every stage of running a script.
+#[derive(Debug)]
+pub enum CaraError {
+    Lex(LexError),
+    Parse(ParseError),
+    Runtime(backend::Error),
+}
+
+impl fmt::Display for CaraError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Lex(error) => write!(f, "lexical error: {error}"),
+            Self::Parse(error) => write!(f, "syntax error: {error}"),
+            Self::Runtime(error) => write!(f, "runtime error: {error}"),
+        }
+    }
+}
+
+/// Lets hosts propagate errors from scripts with `?`. \
+/// Example
+/// ```rust
+/// use cara::backend::Error;
+/// use cara::CaraError;
+///
+/// fn run(src: &str) -> Result<String, Box<dyn std::error::Error>> {
+///     Ok(cara::eval(src)?.to_string())
+/// }
+///
+/// assert_eq!(run("1 + 2").unwrap(), "3");
+/// let error = run("break").unwrap_err();
+/// assert!(matches!(
+///     error.downcast_ref::<CaraError>(),
+///     Some(CaraError::Runtime(Error::NotInLoop))
+/// ));
+/// assert_eq!(error.to_string(), "runtime error: using break/continue outside of loop");
+/// ```
+#[cfg(feature = "std")]
+impl std::error::Error for CaraError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Lex(error) => Some(error),
+            Self::Parse(error) => Some(error),
+            Self::Runtime(error) => Some(error),
+        }
+    }
+}
+
+impl From<ParseError> for CaraError {
+    fn from(error: ParseError) -> Self {
+        match error {
+            ParseError::Lex(error) => Self::Lex(error),
+            error => Self::Parse(error),
+        }
+    }
+}
+
+impl From<backend::Error> for CaraError {
+    fn from(error: backend::Error) -> Self {
+        Self::Runtime(error)
+    }
+}
+
+/// A parsed script with its string table, which can be run many times without
+/// parsing it again. With the `serde` feature it can also be saved and loaded
+/// again, with numbers stored as decimal strings. \
+/// Example
+/// ```rust
+/// use cara::backend::CrValue;
+/// use cara::Program;
+///
+/// let program = Program::compile("var y = x * x; y + 1").unwrap();
+/// for (x, expected) in [(3, 10), (5, 26)] {
+///     let mut interpreter = program.interpreter();
+///     interpreter.set_variable("x", CrValue::Number(x.into()));
+///     assert_eq!(program.run(&mut interpreter).unwrap(), CrValue::Number(expected.into()));
+/// }
+/// ```
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Program {
+    ast: AstNodes,
+    strings: Vec<String>,
+}
+
+impl Program {
+    /// Lexes and parses a script.
+    pub fn compile(src: &str) -> Result<Self, ParseError> {
+        let lexer = Lexer::new(String::from(src));
+        let (ast, strings) = Parser::new(lexer).parse_compile_unit()?;
+        Ok(Self { ast, strings })
+    }
+
+    /// Creates an interpreter which can run the program.
+    #[must_use]
+    pub fn interpreter(&self) -> Interpreter {
+        Interpreter::new(self.strings.clone())
+    }
+
+    /// Runs the program and returns the value of its last statement. The interpreter
+    /// must come from [`Program::interpreter`], since names are looked up by their ids
+    /// in the program's string table.
+    pub fn run(&self, interpreter: &mut Interpreter) -> backend::Result<CrValue> {
+        interpreter.visit(&self.ast)
+    }
+
+    /// Returns the parsed script.
+    #[must_use]
+    pub const fn ast(&self) -> &AstNodes {
+        &self.ast
+    }
+
+    /// Returns the names and string literals of the script, indexed by their ids.
+    #[must_use]
+    pub fn string_table(&self) -> &[String] {
+        &self.strings
+    }
+}
+
+/// Runs source given a line at a time, like an interactive prompt does. Variables
+/// and functions are kept from one line to the next, and a line which leaves a
+/// block, list or string open waits for the lines which finish it. \
+/// Example
+/// ```rust
+/// use cara::backend::CrValue;
+/// use cara::Repl;
+///
+/// let mut repl = Repl::new();
+/// assert_eq!(repl.feed("var x = 20;").unwrap().unwrap(), CrValue::Void);
+/// assert!(repl.feed("fn double(n) {").is_none());
+/// assert!(repl.is_pending());
+/// assert!(repl.feed("return n * 2; }").unwrap().is_ok());
+/// assert_eq!(repl.feed("double(x) + 2").unwrap().unwrap(), CrValue::Number(42.into()));
+/// assert!(repl.feed("x / 0").unwrap().is_err());
+/// assert_eq!(repl.feed("x").unwrap().unwrap(), CrValue::Number(20.into()));
+/// ```
+pub struct Repl {
+    interpreter: Interpreter,
+    pending: String,
+}
+
+impl Repl {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            interpreter: Interpreter::new(Vec::new()),
+            pending: String::new(),
+        }
+    }
+
+    /// Returns the interpreter the lines run in, to set its printer or limits.
+    pub fn interpreter_mut(&mut self) -> &mut Interpreter {
+        &mut self.interpreter
+    }
+
+    /// Returns whether the lines fed so far are waiting for more to finish them.
+    #[must_use]
+    pub fn is_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Adds a line of source. Returns `None` if the source isn't finished yet, or else
+    /// runs it and returns the value of its last statement.
+    pub fn feed(&mut self, line: &str) -> Option<Result<CrValue, CaraError>> {
+        self.pending.push_str(line);
+        self.pending.push('\n');
+        let parsed = match self.interpreter.parse_source(&self.pending) {
+            Err(ParseError::UnexpectedEof | ParseError::Lex(LexError::UnterminatedString(_))) => {
+                return None;
+            }
+            parsed => parsed,
+        };
+        self.pending.clear();
+        Some(match parsed {
+            Ok(ast) => self.interpreter.visit(&ast).map_err(CaraError::from),
+            Err(error) => Err(error.into()),
+        })
+    }
+}
+
+impl Default for Repl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Runs a whole script and returns the value of its last statement. \
+/// Example
+/// ```rust
+/// use cara::backend::CrValue;
+///
+/// let value = cara::eval("fn f() { return 42; } f()").unwrap();
+/// assert_eq!(value, CrValue::Number(42.into()));
+///
+/// assert!(cara::eval("var = ;").is_err());
+/// ```
+pub fn eval(src: &str) -> Result<CrValue, CaraError> {
+    let program = Program::compile(src)?;
+    Ok(program.run(&mut program.interpreter())?)
+}
+
+/// Runs a whole script like [`eval`] and returns everything it printed. \
+/// Example
+/// ```rust
+/// let output = cara::run_and_capture("for i in (0, 3) { write(i); } print();").unwrap();
+/// assert_eq!(output, "012\n");
+/// ```
+pub fn run_and_capture(src: &str) -> Result<String, CaraError> {
+    let program = Program::compile(src)?;
+    let output = Rc::new(RefCell::new(String::new()));
+    let buffer = output.clone();
+    let mut interpreter = program.interpreter();
+    interpreter.set_printer(Box::new(move |args| {
+        // Writing to a string can't fail.
+        let _ = buffer.borrow_mut().write_fmt(args);
+    }));
+    program.run(&mut interpreter)?;
+    Ok(output.take())
+}
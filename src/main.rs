@@ -1,33 +1,44 @@
 use std::env::args;
 use std::fs::File;
-use std::io::Read;
+use std::io::{stdin, stdout, IsTerminal, Read, Write};
 use std::process::exit;
 
-use cara::backend::Interpreter;
+use cara::backend::{CrValue, Interpreter, Reader};
 use cara::frontend::{Lexer, Parser};
+use cara::Repl;
 
 fn main() {
-    let mut code = String::new();
-
     let path = args().nth(1).unwrap_or_else(|| {
         eprintln!("Unable to get cara source file path!");
         exit(1);
     });
 
-    let mut file = File::open(&path).unwrap_or_else(|_| {
-        eprintln!("Unable to find cara source file!");
-        exit(1);
-    });
-
-    if file.read_to_string(&mut code).is_err() {
-        eprintln!("Unable to read cara source file!");
-        exit(1);
-    }
+    // Source given with `-e` is run like a file, but its value is printed too.
+    let (code, print_value) = match path.as_str() {
+        "-i" | "--repl" => {
+            repl();
+            return;
+        }
+        "-e" | "--eval" => {
+            let code = args().nth(2).unwrap_or_else(|| {
+                eprintln!("Unable to get cara source after {path}!");
+                exit(1);
+            });
+            (code, true)
+        }
+        _ => (read_source(&path), false),
+    };
 
     let lexer = Lexer::new(code);
     let mut parser = Parser::new(lexer);
 
-    let (ast, strings) = parser.parse_compile_unit();
+    let (ast, strings) = match parser.parse_compile_unit() {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("on syntax error: {e}");
+            exit(1);
+        }
+    };
 
     #[cfg(debug_assertions)]
     println!("{:#?}", ast);
@@ -35,19 +46,74 @@ fn main() {
     cara::backend::set_printer(|args| print!("{}", args));
 
     let mut interpreter = Interpreter::new(strings);
+    interpreter.set_reader(stdin_reader());
 
     match interpreter.visit(&ast) {
-        #[cfg(debug_assertions)]
-        Ok(value) => println!("{:?}", value),
-        #[cfg(not(debug_assertions))]
-        Ok(_) => (),
+        Ok(value) => {
+            #[cfg(debug_assertions)]
+            println!("{:?}", value);
+            if print_value && value != CrValue::Void {
+                println!("{value}");
+            }
+        }
         Err(e) => {
-            eprintln!("on runtime error: {e}");
-            eprintln!("variables:");
-            for (i, name) in interpreter.string_table().iter().enumerate() {
-                eprintln!(" {i}:\t{name}");
+            match interpreter.error_span() {
+                Some(span) => eprintln!("on runtime error at {span}: {e}"),
+                None => eprintln!("on runtime error: {e}"),
             }
-            eprintln!("{e}");
+            exit(1);
+        }
+    }
+}
+
+fn read_source(path: &str) -> String {
+    let mut code = String::new();
+
+    let mut file = File::open(path).unwrap_or_else(|_| {
+        eprintln!("Unable to find cara source file!");
+        exit(1);
+    });
+
+    if file.read_to_string(&mut code).is_err() {
+        eprintln!("Unable to read cara source file!");
+        exit(1);
+    }
+
+    code
+}
+
+/// Reads lines of source from stdin and runs each statement once it's complete,
+/// printing the values of expressions.
+fn repl() {
+    cara::backend::set_printer(|args| print!("{}", args));
+
+    let mut repl = Repl::new();
+    repl.interpreter_mut().set_reader(stdin_reader());
+    let interactive = stdin().is_terminal();
+
+    loop {
+        if interactive {
+            print!("{}", if repl.is_pending() { "... " } else { "> " });
+            let _ = stdout().flush();
+        }
+        let mut line = String::new();
+        match stdin().read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        match repl.feed(line.trim_end_matches(['\n', '\r'])) {
+            None | Some(Ok(CrValue::Void)) => {}
+            Some(Ok(value)) => println!("{value}"),
+            Some(Err(e)) => eprintln!("on {e}"),
         }
     }
 }
+
+/// Makes `input()` read lines from stdin.
+fn stdin_reader() -> Reader {
+    Box::new(|| {
+        // Show any prompt printed without a newline before waiting for input.
+        let _ = stdout().flush();
+        stdin().lines().next()?.ok()
+    })
+}
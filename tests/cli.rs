@@ -0,0 +1,83 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+fn cara(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_cara"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// Writes `src` to a script file named after the test which uses it.
+fn script(name: &str, src: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("cara-{}-{name}.cara", std::process::id()));
+    fs::write(&path, src).unwrap();
+    path
+}
+
+#[test]
+fn bad_scripts_exit_with_an_error() {
+    for (name, src, message) in [
+        ("syntax", "var = ;", "on syntax error: expected identifier"),
+        (
+            "runtime",
+            "var a = 1 / 0;",
+            "on runtime error at line 1, column 11",
+        ),
+    ] {
+        let path = script(name, src);
+        let output = cara(&[path.to_str().unwrap()]);
+        fs::remove_file(&path).unwrap();
+
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert_eq!(output.status.code(), Some(1), "{name}");
+        assert!(stderr.contains(message), "{name}: {stderr}");
+        assert!(!stderr.contains("panicked"), "{name}: {stderr}");
+    }
+
+    let output = cara(&["/nonexistent/script.cara"]);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn repl_runs_statements_from_stdin() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_cara"))
+        .arg("-i")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let input = "var x = 2;\nx * 3\nfn f(a) {\n  return a + 1;\n}\nf(x)\nnope\nprint(\"done\");\n";
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "6\n3\ndone\n");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr, "on runtime error: symbol not found: nope\n");
+}
+
+#[test]
+fn eval_flag_runs_its_argument() {
+    // Debug builds also print the AST and the result, so the printed line is searched for.
+    let output = cara(&["-e", "print(2+2);"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.lines().any(|line| line == "4"), "{stdout}");
+
+    let output = cara(&["-e", "var a = 20; a + 22"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.lines().any(|line| line == "42"), "{stdout}");
+
+    let output = cara(&["-e"]);
+    assert_eq!(output.status.code(), Some(1));
+}
@@ -0,0 +1,33 @@
+#![cfg(feature = "serde")]
+
+use cara::backend::CrValue;
+use cara::Program;
+
+#[test]
+fn programs_run_the_same_after_a_round_trip() {
+    let src = r#"
+        fn fact(n) { return n < 2 ? 1 : n * fact(n - 1); }
+        var big = fact(30);
+        var words = ["a", "b"];
+        for i in (0, 2) { push(words, "c"); }
+        [big == 265252859812191058636308480000000, -2 ** 2, words, 'x', 1.5, {"k": len(words)}]
+    "#;
+    let program = Program::compile(src).unwrap();
+    let json = serde_json::to_string(&program).unwrap();
+    assert!(json.contains("\"265252859812191058636308480000000\""));
+
+    let reloaded: Program = serde_json::from_str(&json).unwrap();
+    assert_eq!(reloaded.string_table(), program.string_table());
+    let expected = program.run(&mut program.interpreter()).unwrap();
+    let value = reloaded.run(&mut reloaded.interpreter()).unwrap();
+    assert_eq!(value, expected);
+    assert_ne!(value, CrValue::Void);
+}
+
+#[test]
+fn numbers_must_be_decimal_strings() {
+    let program = Program::compile("12").unwrap();
+    let json = serde_json::to_string(&program).unwrap();
+    let broken = json.replace("\"12\"", "\"twelve\"");
+    assert!(serde_json::from_str::<Program>(&broken).is_err());
+}